@@ -1,6 +1,20 @@
 use crate::engine::{
-    input::{Action::*, MouseB::*, MouseM::*},
-    world::Point,
+    bookmarks::{Bookmarks, Follow},
+    camera::WORLD,
+    console::{self, Command},
+    input::{Action::{self, *}, MouseM::*},
+    recording::{self, Playback, Recording},
+    world::{
+        ant::{Ant, Turmite},
+        locate,
+        pattern::Pattern,
+        render::{Painter, RenderMode},
+        state::CellState,
+        stats,
+        tile::Tile,
+        Point,
+    },
+    Camera,
     InputHandler,
     World,
 };
@@ -11,34 +25,61 @@ use gfx_graphics::{GfxGraphics, Texture, TextureContext};
 use graphics::{
     clear,
     glyph_cache::rusttype::GlyphCache,
+    image,
     rectangle,
     text,
     Context,
     Line,
+    Rectangle,
     Transformed,
 };
-use piston_window::{Event, OpenGL, PistonWindow, RenderArgs, Size, Window};
+use piston_window::{
+    Event,
+    OpenGL,
+    PistonWindow,
+    RenderArgs,
+    Size,
+    Window,
+};
 use sdl2_window::Sdl2Window;
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use texture::{CreateTexture, Filter, Format, TextureSettings, UpdateTexture};
 
-pub struct App {
+pub struct App<S: CellState = Tile> {
     pub title:          String,
     pub opengl:         OpenGL,
     pub fps:            FPSCounter,
     pub ups:            f64,
-    pub focus:          [f64; 4],
     pub capture_cursor: bool,
     pub assets:         PathBuf,
-    pub w:              f64,
-    pub h:              f64,
-    pub ar:             f64,
     pub stats:          bool,
-    pub world:          World,
+    pub inspect:        bool,
+    pub minimap:        bool,
+    pub grid:           bool,
+    pub borders:        bool,
+    pub hilbert:        bool,
+    pub paused:         bool,
+    pub mode:           RenderMode,
+    pub painted:        (RenderMode, u32, u64),
+    pub world:          World<S>,
     pub input:          InputHandler,
-    pub size:           (f64, f64),
+    pub camera:         Camera,
+    pub bookmarks:      Bookmarks,
+    pub bookmark:       Option<String>,
+    pub follow:         Follow,
+    pub last_put:       Option<Point<Point<u16>, usize>>,
+    pub recording:      Option<Recording<S>>,
+    pub playback:       Option<Playback>,
+    pub console:        console::Console,
+    pub textures:       TextureContext<Factory, Resources, CommandBuffer>,
+    pub cache:          HashMap<Point<u16>, Texture<Resources>>,
 }
 
-impl App {
+impl<S: CellState> App<S> {
     pub fn toggle_stats(&mut self) { self.stats = !self.stats; }
 
     pub fn resize(
@@ -46,21 +87,11 @@ impl App {
         window: &PistonWindow<Sdl2Window>,
     ) {
         let Size { width, height } = window.window.draw_size();
-        self.w = width;
-        self.h = height;
-        self.ar = width / height;
+        self.camera.resize(width, height);
     }
 
     pub fn tick(&mut self) -> usize { self.fps.tick() }
 
-    pub fn size(
-        &mut self,
-        size: &f64,
-    ) {
-        const FACTOR: f64 = 1.3;
-        self.size.1 = size * self.size.0.abs().ln_1p().exp() * FACTOR;
-    }
-
     pub fn draw(
         &mut self,
         c: &Context,
@@ -74,9 +105,20 @@ impl App {
     ) {
         clear([0.0, 0.0, 0.0, 1.0], g);
         self.draw_tiles(c, g);
+        self.draw_overlays(c, g, glyphs);
+        self.draw_ants(c, g);
         if self.stats {
             self.stats(c, g, glyphs);
         }
+        if self.minimap {
+            self.draw_minimap(c, g);
+        }
+        if self.inspect {
+            self.inspector(c, g, glyphs);
+        }
+        if self.console.open {
+            self.draw_console(c, g, glyphs);
+        }
     }
 
     pub fn draw_tiles(
@@ -84,175 +126,677 @@ impl App {
         c: &Context,
         g: &mut GfxGraphics<Resources, CommandBuffer>,
     ) {
-        let size = self.size.0;
-        let transform = c
-            .transform
-            .trans(self.focus[0] * size * 32., self.focus[1] * size * 32.);
-
-        // let con = Line::new([1., 1., 1., 0.8], 0.5);
-        // let mut loc = [0f64; 4];
-        let camera = (
-            (-self.focus[0]).trunc(),
-            (-self.focus[1]).trunc(),
-            ((self.w / size) / 31.).trunc(),
-            ((self.h / size) / 31.).trunc(),
+        let size = self.camera.zoom;
+        let [x, y] = self.camera.to_screen([0., 0.]);
+        let transform = c.transform.trans(x, y);
+
+        let visible = self.camera.visible();
+        let lod = self.lod();
+        let painter = Painter {
+            mode:       self.mode,
+            palette:    &self.world.palette,
+            generation: self.world.generation,
+        };
+        let painted = (
+            self.mode,
+            painter.palette.active().id,
+            if self.mode.animated() { painter.generation } else { 0 },
         );
-        self.world
-            .chunks
-            .iter()
-            .filter(|&(_, t)| t.on_screen(camera))
+        let chunks = &mut self.world.chunks;
+        if self.painted != painted {
+            chunks.values_mut().for_each(|chunk| chunk.changed = true);
+            self.painted = painted;
+        }
+        let cache = &mut self.cache;
+        let textures = &mut self.textures;
+        if cache.len() > chunks.len() {
+            cache.retain(|p, _| chunks.contains_key(p));
+        }
+        let settings = TextureSettings::new().filter(Filter::Nearest);
+        chunks
+            .iter_mut()
+            .filter(|(_, t)| visible.map_or(false, |v| t.on_screen(v)))
             .for_each(|(&p, chunk)| {
-                chunk.tiles.iter().filter(|&t| t.members > 0).for_each(|t| {
-                    let tile = (p * 32. + t.pos) * size;
-                    let rect = rectangle::square(tile.0, tile.1, size);
-                    rectangle(t.color(), rect, transform, g);
-                    // loc[2] = tile.0;
-                    // loc[3] = tile.1;
-                    // con.draw(loc, &c.draw_state, transform, g);
-                    // loc.rotate_left(2);
-                })
+                let origin = p * 32. * size;
+                if lod > 1 {
+                    let block = size * lod as f64;
+                    chunk.blocks(lod, &painter).iter().for_each(|(b, color)| {
+                        let tile = (p * 32. + *b) * size;
+                        let rect = rectangle::square(tile.0, tile.1, block);
+                        rectangle(*color, rect, transform, g);
+                    });
+                    return;
+                }
+                if chunk.changed || !cache.contains_key(&p) {
+                    let pixels = chunk.rgba(&painter);
+                    match cache.get_mut(&p) {
+                        Some(texture) => texture
+                            .update(textures, Format::Rgba8, &pixels, [0, 0], [32, 32])
+                            .expect("Couldn't update chunk texture."),
+                        None => {
+                            let texture = Texture::create(
+                                textures,
+                                Format::Rgba8,
+                                &pixels,
+                                [32, 32],
+                                &settings,
+                            )
+                            .expect("Couldn't create chunk texture.");
+                            cache.insert(p, texture);
+                        }
+                    }
+                    chunk.changed = false;
+                }
+                image(
+                    &cache[&p],
+                    transform.trans(origin.0, origin.1).scale(size, size),
+                    g,
+                );
             });
 
         let cell_edge = Line::new([1., 0.3, 0., 1.], 1.);
         const TOP: f64 = 0.;
-        let x2 = (u16::MAX as f64 + 1.) * size * 32. - 1.;
+        let x2 = WORLD * size - 1.;
         cell_edge.draw([TOP, TOP, TOP, x2], &c.draw_state, transform, g);
         cell_edge.draw([TOP, TOP, x2, TOP], &c.draw_state, transform, g);
         cell_edge.draw([TOP, x2, x2, x2], &c.draw_state, transform, g);
         cell_edge.draw([x2, TOP, x2, x2], &c.draw_state, transform, g);
-        let chu = size * 32.;
-        cell_edge.draw([TOP, TOP, TOP, chu], &c.draw_state, transform, g);
-        cell_edge.draw([TOP, TOP, chu, TOP], &c.draw_state, transform, g);
-        cell_edge.draw([TOP, chu, chu, chu], &c.draw_state, transform, g);
-        cell_edge.draw([chu, TOP, chu, chu], &c.draw_state, transform, g);
     }
 
-    pub fn update(&mut self) {
-        let step = self.size.1 / self.ups;
-        self.size.0 = if self.size.0 + step > 1. {
-            self.size.0 + step
-        } else {
-            1.
-        };
-        self.size.1 = if step.abs() > self.ups.recip() {
-            self.size.1 - step
-        } else {
-            0.
+    pub fn draw_overlays(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+        glyphs: &mut GlyphCache<
+            'static,
+            TextureContext<Factory, Resources, CommandBuffer>,
+            Texture<Resources>,
+        >,
+    ) {
+        const GRID_PIXELS: f64 = 8.;
+        const BORDER_PIXELS: f64 = 16.;
+        const LABEL_PIXELS: f64 = 96.;
+        let size = self.camera.zoom;
+        let [x, y] = self.camera.to_screen([0., 0.]);
+        let transform = c.transform.trans(x, y);
+        let (min, max) = match self.camera.visible() {
+            Some(range) => range,
+            None => return,
         };
+        let (first, last) = (
+            [min.0 as f64 * 32., min.1 as f64 * 32.],
+            [(max.0 as f64 + 1.) * 32., (max.1 as f64 + 1.) * 32.],
+        );
+
+        if self.grid && size >= GRID_PIXELS {
+            let line = Line::new([1., 1., 1., 0.15], 0.5);
+            let span = self.camera.span();
+            let from = [self.camera.pos[0].floor(), self.camera.pos[1].floor()];
+            let to = [
+                (self.camera.pos[0] + span[0]).ceil().min(last[0]),
+                (self.camera.pos[1] + span[1]).ceil().min(last[1]),
+            ];
+            let from = [from[0].max(0.), from[1].max(0.)];
+            let mut t = from[0];
+            while t <= to[0] {
+                let l = [t * size, from[1] * size, t * size, to[1] * size];
+                line.draw(l, &c.draw_state, transform, g);
+                t += 1.;
+            }
+            let mut t = from[1];
+            while t <= to[1] {
+                let l = [from[0] * size, t * size, to[0] * size, t * size];
+                line.draw(l, &c.draw_state, transform, g);
+                t += 1.;
+            }
+        }
+
+        if self.borders && size * 32. >= BORDER_PIXELS {
+            let line = Line::new([1., 0.3, 0., 0.6], 0.75);
+            for cx in min.0..=max.0 {
+                let t = cx as f64 * 32. * size;
+                let l = [t, first[1] * size, t, last[1] * size];
+                line.draw(l, &c.draw_state, transform, g);
+            }
+            for cy in min.1..=max.1 {
+                let t = cy as f64 * 32. * size;
+                let l = [first[0] * size, t, last[0] * size, t];
+                line.draw(l, &c.draw_state, transform, g);
+            }
+            if size * 32. >= LABEL_PIXELS {
+                for cx in min.0..=max.0 {
+                    for cy in min.1..=max.1 {
+                        let pos = Point(cx, cy) * 32. * size;
+                        text::Text::new_color([1., 0.3, 0., 0.8], 10)
+                            .draw(
+                                &format!("{} {}", cx, cy),
+                                glyphs,
+                                &c.draw_state,
+                                transform.trans(pos.0 + 3., pos.1 + 12.),
+                                g,
+                            )
+                            .unwrap();
+                    }
+                }
+            }
+        }
+
+        if self.hilbert {
+            // `World::hilbert` keeps the chunks sorted along the curve.
+            let line = Line::new([0.3, 0.6, 1., 0.8], 1.);
+            let corners = self
+                .world
+                .chunks
+                .keys()
+                .map(|&p| p * 32. * size)
+                .collect::<Vec<_>>();
+            let half = 16. * size;
+            corners.windows(2).for_each(|w| {
+                let (a, b) = (w[0], w[1]);
+                let l = [a.0 + half, a.1 + half, b.0 + half, b.1 + half];
+                line.draw(l, &c.draw_state, transform, g);
+            });
+            corners.iter().enumerate().for_each(|(i, a)| {
+                let rect = rectangle::centered_square(a.0 + half, a.1 + half, 2.);
+                rectangle([0.3, 0.6, 1., 1.], rect, transform, g);
+                if size * 32. >= LABEL_PIXELS {
+                    text::Text::new_color([0.3, 0.6, 1., 1.], 10)
+                        .draw(
+                            &i.to_string(),
+                            glyphs,
+                            &c.draw_state,
+                            transform.trans(a.0 + half + 4., a.1 + half - 4.),
+                            g,
+                        )
+                        .unwrap();
+                }
+            });
+        }
+    }
+
+    /// Ants as squares over their tiles, with a tick towards their heading.
+    pub fn draw_ants(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+    ) {
+        let size = self.camera.zoom;
+        let [x, y] = self.camera.to_screen([0., 0.]);
+        let transform = c.transform.trans(x, y);
+        let line = Line::new([1., 1., 1., 1.], (size / 8.).max(0.5));
+        self.world.ants.iter().for_each(|ant| {
+            let [tx, ty] = ant.pos.coords();
+            let (cx, cy) = ((tx as f64 + 0.5) * size, (ty as f64 + 0.5) * size);
+            let half = (size * 0.3).max(2.);
+            let rect = rectangle::centered_square(cx, cy, half);
+            rectangle([1., 0.5, 0., 1.], rect, transform, g);
+            let (dx, dy) = ant.heading.offset();
+            let tip = [cx + dx as f64 * half * 1.5, cy + dy as f64 * half * 1.5];
+            line.draw([cx, cy, tip[0], tip[1]], &c.draw_state, transform, g);
+        });
+    }
 
-        self.focus[0] += self.focus[2];
-        self.focus[1] += self.focus[3];
-        self.focus[2] -= self.focus[2];
-        self.focus[3] -= self.focus[3];
-
-        // dbg!(self.size);
-        // let xrate = self.focus[2].abs().acos() / 10.;
-        // let yrate = self.focus[3].abs().acos() / 10.;
-        // if self.focus[2] != 0. {
-        //     self.focus[0] += xrate.copysign(self.focus[2]);
-        //     self.focus[2] = if xrate > 0.1 {
-        //         self.focus[2] - xrate.copysign(self.focus[2])
-        //     } else {
-        //         self.focus[0] += self.focus[2];
-        //         0.
-        //     };
-        // }
-        // if self.focus[3] != 0. {
-        //     self.focus[1] += yrate.copysign(self.focus[3]);
-        //     self.focus[3] = if yrate > self.focus[3].abs() / self.ups {
-        //         self.focus[3] - yrate.copysign(self.focus[3])
-        //     } else {
-        //         self.focus[1] += self.focus[3];
-        //         0.
-        //     };
-        // }
-        // dbg!(self.focus[2]);
-
-        self.world.update()
+    /// Side of the block of tiles covering at least `LOD_PIXELS` on screen.
+    pub fn lod(&self) -> usize {
+        const LOD_PIXELS: f64 = 1.;
+        let k = (LOD_PIXELS / self.camera.zoom).ceil().max(1.) as usize;
+        k.next_power_of_two().min(32)
+    }
+
+    pub fn update(&mut self) {
+        self.camera.update(self.ups);
+        match self.follow {
+            Follow::Off => {}
+            Follow::Centroid => {
+                if let Some(centroid) = self.world.centroid() {
+                    self.camera.track(centroid, None);
+                }
+            }
+            Follow::Bounds => {
+                if let Some((min, max)) = self.world.bounds() {
+                    const MARGIN: f64 = 8.;
+                    let [w, h] = self.camera.viewport;
+                    let zoom = (w / (max[0] - min[0] + MARGIN))
+                        .min(h / (max[1] - min[1] + MARGIN));
+                    let center = [(min[0] + max[0]) / 2., (min[1] + max[1]) / 2.];
+                    self.camera.track(center, Some(zoom));
+                }
+            }
+        }
+
+        if let Some(playback) = &mut self.playback {
+            playback.apply(&mut self.world);
+            if playback.done() {
+                self.playback = None;
+            }
+        }
+        if !self.paused {
+            self.world.update()
+        }
+        if let Some(e) = self.world.rule.error() {
+            self.console.log(e);
+        }
     }
 
     pub fn event(
         &mut self,
         e: &Event,
     ) {
-        match self.input.event(e) {
+        let action = self.input.event(e);
+        if self.console.open {
+            self.camera.pan(0., 0.);
+            if action == Console {
+                self.console.toggle();
+            } else if let Some(line) = self.console.input(e) {
+                self.run(&line);
+            }
+            return;
+        }
+        match &action {
+            N | NE | E | SE | S | SW | W | NW => {}
+            _ => self.camera.pan(0., 0.),
+        }
+        if action != Put {
+            self.last_put = None;
+        }
+        let cursor = self.input.cursor;
+        let motion = [self.input.motion()[0], self.input.motion()[1]];
+        // Mouse edits, steps and rule changes are recorded where they take
+        // effect.
+        match action {
+            Put | Remove | Step | Rule(_) | PlaceTurmite(_) => {}
+            _ if recording::edits(&action) => {
+                let pos = self.camera.tile_at(cursor);
+                self.record(action.clone(), pos);
+            }
+            _ => {}
+        }
+        match action {
             Pass => {}
             Exit => self.exit(),
-            Stats => self.stats = self.input.repeat(),
-            N => self.focus[3] += 0.02,
-            NE => {
-                self.focus[3] += 0.02;
-                self.focus[2] -= 0.02;
-            }
-            E => self.focus[2] -= 0.02,
-            SE => {
-                self.focus[3] -= 0.02;
-                self.focus[2] -= 0.02;
-            }
-            S => self.focus[3] -= 0.02,
-            SW => {
-                self.focus[3] -= 0.02;
-                self.focus[2] += 0.02;
-            }
-            W => self.focus[2] += 0.02,
-            NW => {
-                self.focus[3] += 0.02;
-                self.focus[2] += 0.02;
-            }
+            Clear => self.world.end(),
+            Stats => self.toggle_stats(),
+            Inspect => self.inspect = !self.inspect,
+            N => self.camera.pan(0., -1.),
+            NE => self.camera.pan(1., -1.),
+            E => self.camera.pan(1., 0.),
+            SE => self.camera.pan(1., 1.),
+            S => self.camera.pan(0., 1.),
+            SW => self.camera.pan(-1., 1.),
+            W => self.camera.pan(-1., 0.),
+            NW => self.camera.pan(-1., -1.),
             ResetZoom => {
-                self.size = (20., 0.);
-                if self.input.repeat() {
-                    self.focus = [0.0, 0.0, 0.0, 0.0];
-                } else {
-                    self.focus = [
-                        -(u16::MAX as f64) + (self.w / self.size.0 / 32. - 1.),
-                        -(u16::MAX as f64) + (self.h / self.size.0 / 32. - 1.),
-                        0.0,
-                        0.0,
-                    ];
+                self.camera.zoom = 20.;
+                self.camera.zoom_vel = 0.;
+                self.camera.vel = [0.; 2];
+                let span = self.camera.span();
+                self.camera.pos = [WORLD - span[0], WORLD - span[1]];
+            }
+            ResetOrigin => {
+                self.camera.zoom = 20.;
+                self.camera.zoom_vel = 0.;
+                self.camera.vel = [0.; 2];
+                self.camera.pos = [0., 0.];
+            }
+            Pause => self.paused = !self.paused,
+            Step => {
+                if self.paused {
+                    self.record(Step, None);
+                    self.world.update();
+                }
+            }
+            Record => match self.recording.take() {
+                Some(recording) => recording.save_recording(recording::PATH),
+                None => {
+                    self.playback = None;
+                    self.recording = Some(Recording::start(&self.world));
+                }
+            },
+            Console => self.console.toggle(),
+            Replay => {
+                let mut recording = Recording::default();
+                recording.load_recording(recording::PATH);
+                self.recording = None;
+                self.playback = Some(Playback::start(recording, &mut self.world));
+            }
+            Select(i) => self.world.palette.select(i),
+            SaveBookmark(name) => self.bookmarks.save(&name, &self.camera),
+            Bookmark(name) => self.jump(name),
+            NextBookmark => {
+                let next = self
+                    .bookmarks
+                    .next(self.bookmark.as_deref())
+                    .map(String::from);
+                if let Some(name) = next {
+                    self.jump(name);
+                }
+            }
+            NextMode => self.mode = self.mode.next(),
+            Rule(name) => {
+                if let Err(e) = self.set_rule(&name) {
+                    self.console.log(e);
+                }
+            }
+            NextPreset => {
+                let presets = S::presets();
+                let name = self.world.rule.name();
+                let next = presets
+                    .iter()
+                    .position(|&p| p == name)
+                    .map_or(0, |i| (i + 1) % presets.len());
+                if let Some(preset) = presets.get(next) {
+                    if let Err(e) = self.set_rule(preset) {
+                        self.console.log(e);
+                    }
+                }
+            }
+            Grid => self.grid = !self.grid,
+            Borders => self.borders = !self.borders,
+            Hilbert => self.hilbert = !self.hilbert,
+            Minimap => self.minimap = !self.minimap,
+            FollowMode => self.follow = self.follow.next(),
+            Put => {
+                if let Some(world) = self.on_minimap(cursor) {
+                    self.follow = Follow::Off;
+                    self.camera.look_at(world);
+                } else if let Some(pos) = self.camera.tile_at(cursor) {
+                    // Paint each tile once per stroke rather than every event.
+                    if self.input.fresh() || self.last_put != Some(pos) {
+                        self.world.put(&pos);
+                        self.record(Put, Some(pos));
+                        self.last_put = Some(pos);
+                    }
+                }
+            }
+            Remove => {
+                if let Some(pos) = self.camera.tile_at(cursor) {
+                    if self.world.tile(&pos).map_or(false, |t| t.alive()) {
+                        self.world.remove(&pos);
+                        self.record(Remove, Some(pos));
+                    }
+                }
+            }
+            Pick => {
+                if let Some(pos) = self.camera.tile_at(cursor) {
+                    self.world.pick(&pos);
+                }
+            }
+            PlaceAnt => {
+                if let Some(pos) = self.camera.tile_at(cursor) {
+                    self.world.place_ant(&pos);
+                }
+            }
+            PlaceTurmite(rule) => {
+                if let Some(pos) = self.camera.tile_at(cursor) {
+                    if let Err(e) = self.place_turmite(pos, &rule) {
+                        self.console.log(e);
+                    }
+                }
+            }
+            ClearAnts => self.world.ants.clear(),
+            Pan => {
+                if let Some(Drag(x1, y1, x2, y2)) = motion[1] {
+                    self.camera.drag(x2 - x1, y2 - y1);
+                }
+            }
+            LookAt => self.camera.look_at(self.camera.to_world(cursor)),
+            ZoomIn => {
+                if let Some(Scroll(scroll)) = motion[0] {
+                    self.camera.zoom_by(scroll.abs(), cursor);
+                }
+            }
+            ZoomOut => {
+                if let Some(Scroll(scroll)) = motion[0] {
+                    self.camera.zoom_by(-scroll.abs(), cursor);
                 }
             }
+            NextField => self.world.palette.next(),
+            PrevField => self.world.palette.prev(),
         };
+    }
 
-        #[allow(unused_variables)]
-        for button in self.input.mouse() {
-            match button {
-                LMB(x, y) => self.world.put(&self.get_pos(x, y)),
-                RMB(x, y) => self.world.remove(&self.get_pos(x, y)),
-                MMB(x, y) => {
-                    self.world.end();
-                    self.focus = [
-                        -(u16::MAX as f64 - self.w) / 2.,
-                        -(u16::MAX as f64 - self.h) / 2.,
-                        0.0,
-                        0.0,
-                    ];
+    /// Runs a console command, logging it along with any error.
+    pub fn run(
+        &mut self,
+        line: &str,
+    ) {
+        self.console.log(format!("> {}", line));
+        let result = match Command::parse(line) {
+            Ok(Some(command)) => self.execute(command),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.console.log(e);
+        }
+    }
+
+    /// Runs every line of a script file as a console command.
+    pub fn exec(
+        &mut self,
+        path: &str,
+    ) -> Result<(), String> {
+        let script =
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        script.lines().for_each(|line| self.run(line));
+        Ok(())
+    }
+
+    pub fn execute(
+        &mut self,
+        command: Command,
+    ) -> Result<(), String> {
+        match command {
+            Command::Step(n) => (0..n).for_each(|_| {
+                self.record(Step, None);
+                self.world.update();
+            }),
+            Command::Load {
+                path,
+                at: Some([x, y]),
+            } => {
+                // Patterns are looked up in assets/patterns unless the path
+                // exists as given.
+                let pattern = match Path::new(&path).exists() {
+                    true => Pattern::load(&path)?,
+                    false => {
+                        Pattern::load(self.assets.join("patterns").join(&path))?
+                    }
+                };
+                pattern.cells.iter().for_each(|&([cx, cy], state)| {
+                    let pos = locate(x + cx as i64, y + cy as i64);
+                    self.stamp(pos, Some(state).filter(|_| pattern.multistate));
+                });
+                self.console.log(format!(
+                    "{} cells from {}",
+                    pattern.cells.len(),
+                    path
+                ));
+            }
+            Command::Load { path, at: None } => {
+                if let Some(rule) = self.world.load_world(&path)? {
+                    self.set_rule(&rule)?;
                 }
+                // A recording can't replay from a loaded world.
+                self.recording = None;
+                self.playback = None;
             }
-        }
-        #[allow(unused_variables)]
-        match self.input.motion() {
-            [Some(Scroll(scroll)), _] => {
-                const FACTOR: f64 = 1.3;
-                self.size.1 = scroll * self.size.0.abs().ln_1p().exp() * FACTOR;
+            Command::Rule(name) => self.set_rule(&name)?,
+            Command::Ant { rule, at } => {
+                let [x, y] = match at {
+                    Some(at) => at,
+                    None => {
+                        let span = self.camera.span();
+                        let [x, y] = self.camera.pos;
+                        [(x + span[0] / 2.) as i64, (y + span[1] / 2.) as i64]
+                    }
+                };
+                self.place_turmite(locate(x, y), &rule)?;
             }
-            // [None, Some(Drag(x1, y1, x2, y2))] => {}
-            _ => {}
+            Command::ClearAnts => {
+                self.record(ClearAnts, None);
+                self.world.ants.clear();
+            }
+            Command::Goto(pos) => {
+                self.follow = Follow::Off;
+                self.camera.look_at(pos);
+            }
+            Command::Clear => {
+                self.record(Clear, None);
+                self.world.end();
+            }
+            Command::Save(path) => self.world.save_world(&path)?,
+            Command::Set(name, value) => match name.as_str() {
+                "ups" if value > 0. => self.ups = value,
+                "zoom" => self.camera.zoom = value.max(self.camera.min_zoom()),
+                "accel" => self.camera.motion.accel = value,
+                "friction" => self.camera.motion.friction = value,
+                "max_speed" => self.camera.motion.max_speed = value,
+                _ => return Err(format!("can't set {} to {}", name, value)),
+            },
+            Command::Pause => self.paused = !self.paused,
+            Command::Exec(path) => self.exec(&path)?,
+            Command::LoadKeymap => {
+                let report = self.input.load_keymap();
+                report
+                    .invalid
+                    .iter()
+                    .chain(report.conflicts.iter())
+                    .for_each(|e| self.console.log(e.clone()));
+            }
+            Command::SaveKeymap => self.input.save_keymap(),
         }
+        Ok(())
+    }
+
+    /// Replaces the world's rule by name, along with its palette if it comes
+    /// with one, and records the change.
+    pub fn set_rule(
+        &mut self,
+        name: &str,
+    ) -> Result<(), String> {
+        self.world.set_rule(name)?;
+        self.record(Rule(name.to_string()), None);
+        Ok(())
+    }
+
+    /// Places an ant running the turmite `rule` at `pos`, and records it.
+    fn place_turmite(
+        &mut self,
+        pos: Point<Point<u16>, usize>,
+        rule: &str,
+    ) -> Result<(), String> {
+        let rule = rule.parse::<Turmite>()?;
+        self.record(PlaceTurmite(rule.to_string()), Some(pos));
+        self.world.ants.push(Ant::new(pos, rule));
+        Ok(())
     }
 
-    fn get_pos(
+    /// Paints a pattern cell, selecting the field of its state for the edit
+    /// so that it records and replays like painting by hand.
+    fn stamp(
+        &mut self,
+        pos: Point<Point<u16>, usize>,
+        state: Option<u8>,
+    ) {
+        let active = self.world.palette.selected();
+        let i = state
+            .and_then(|s| {
+                let types = self.world.palette.types();
+                types.iter().position(|t| t.id == s as u32)
+            })
+            .filter(|&i| i != active);
+        if let Some(i) = i {
+            self.world.palette.select(i);
+            self.record(Select(i), None);
+        }
+        self.world.put(&pos);
+        self.record(Put, Some(pos));
+        if i.is_some() {
+            self.world.palette.select(active);
+            self.record(Select(active), None);
+        }
+    }
+
+    /// Adds a world edit to the recording in progress, if any.
+    pub fn record(
+        &mut self,
+        action: Action,
+        pos: Option<Point<Point<u16>, usize>>,
+    ) {
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.world, action, pos);
+        }
+    }
+
+    pub fn jump(
+        &mut self,
+        name: String,
+    ) {
+        if let Some(view) = self.bookmarks.get(&name) {
+            self.camera.set_view(view);
+            self.bookmark = Some(name);
+        }
+    }
+
+    /// Screen rectangle of the minimap, in the bottom left corner.
+    pub fn minimap_rect(&self) -> [f64; 4] {
+        const SIZE: f64 = 160.;
+        [8., self.camera.viewport[1] - SIZE - 8., SIZE, SIZE]
+    }
+
+    /// World position, in tiles, under a screen position on the minimap.
+    pub fn on_minimap(
         &self,
-        x: &f64,
-        y: &f64,
-    ) -> Point<Point<u16>, usize> {
-        let x1 = self.size.0.recip() * x / 32. - self.focus[0];
-        let y1 = self.size.0.recip() * y / 32. - self.focus[1];
-        Point(
-            Point(x1.trunc() as u16, y1.trunc() as u16),
-            ((x1.fract() * 32.).trunc() + ((y1.fract() * 32.).trunc() * 32.))
-                .trunc() as usize,
-        )
+        screen: [f64; 2],
+    ) -> Option<[f64; 2]> {
+        let [x, y, w, h] = self.minimap_rect();
+        let (u, v) = ((screen[0] - x) / w, (screen[1] - y) / h);
+        if self.minimap && (0. ..1.).contains(&u) && (0. ..1.).contains(&v) {
+            Some([u * WORLD, v * WORLD])
+        } else {
+            None
+        }
+    }
+
+    fn draw_minimap(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+    ) {
+        let [x, y, w, h] = self.minimap_rect();
+        rectangle([0.1, 0.1, 0.1, 0.8], [x, y, w, h], c.transform, g);
+
+        // Chunks sharing a minimap pixel are drawn once.
+        let chunks = self
+            .world
+            .chunks
+            .keys()
+            .map(|p| {
+                (
+                    (p.0 as f64 * 32. / WORLD * w) as u32,
+                    (p.1 as f64 * 32. / WORLD * h) as u32,
+                )
+            })
+            .collect::<HashSet<_>>();
+        chunks.iter().for_each(|&(px, py)| {
+            let rect = [x + px as f64, y + py as f64, 1., 1.];
+            rectangle([0.9, 0.9, 0.9, 1.], rect, c.transform, g);
+        });
+
+        let span = self.camera.span();
+        let view = [
+            x + self.camera.pos[0] / WORLD * w,
+            y + self.camera.pos[1] / WORLD * h,
+            (span[0] / WORLD * w).max(1.),
+            (span[1] / WORLD * h).max(1.),
+        ];
+        Rectangle::new_border([1., 0.3, 0., 1.], 0.5).draw(
+            view,
+            &c.draw_state,
+            c.transform,
+            g,
+        );
     }
 
     fn stats<'a>(
@@ -266,12 +810,13 @@ impl App {
         >,
     ) {
         let fps = &self.tick();
+        let [w, h] = self.camera.viewport;
         text::Text::new_color([0.6, 0.6, 0.6, 0.6], 20)
             .draw(
                 &fps.to_string(),
                 glyphs,
                 &c.draw_state,
-                c.transform.trans(self.w - 34., 17.0),
+                c.transform.trans(w - 34., 17.0),
                 g,
             )
             .unwrap();
@@ -280,10 +825,168 @@ impl App {
                 &(self.ups as u32).to_string(),
                 glyphs,
                 &c.draw_state,
-                c.transform.trans(self.w - 34., 36.0),
+                c.transform.trans(w - 34., 36.0),
                 g,
             )
             .unwrap();
+        self.graph(c, g, glyphs);
+    }
+
+    fn graph(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+        glyphs: &mut GlyphCache<
+            'static,
+            TextureContext<Factory, Resources, CommandBuffer>,
+            Texture<Resources>,
+        >,
+    ) {
+        const W: f64 = 256.;
+        const H: f64 = 96.;
+        let history = self.world.history();
+        let [w, h] = self.camera.viewport;
+        let (x, y) = (w - W - 8., h - H - 8.);
+        rectangle([0.1, 0.1, 0.1, 0.7], [x, y, W, H], c.transform, g);
+        if let Some(last) = history.back() {
+            let summary = format!(
+                "gen {}  tiles {}  chunks {}  +{} -{}",
+                last.generation, last.tiles, last.chunks, last.births, last.deaths
+            );
+            text::Text::new_color([0.6, 0.6, 0.6, 0.8], 12)
+                .draw(
+                    &summary,
+                    glyphs,
+                    &c.draw_state,
+                    c.transform.trans(x + 4., y - 4.),
+                    g,
+                )
+                .unwrap();
+        }
+
+        let series = |value: &dyn Fn(&stats::Stats) -> f64,
+                      color: [f32; 4],
+                      g: &mut GfxGraphics<Resources, CommandBuffer>| {
+            let max = history.iter().map(|s| value(s)).fold(1., f64::max);
+            let step = W / (history.len().max(2) - 1) as f64;
+            let line = Line::new(color, 0.75);
+            history.iter().zip(history.iter().skip(1)).enumerate().for_each(
+                |(i, (a, b))| {
+                    line.draw(
+                        [
+                            x + step * i as f64,
+                            y + H - value(a) / max * H,
+                            x + step * (i + 1) as f64,
+                            y + H - value(b) / max * H,
+                        ],
+                        &c.draw_state,
+                        c.transform,
+                        g,
+                    );
+                },
+            );
+        };
+        series(&|s| s.tiles as f64, [1., 1., 1., 0.9], g);
+        self.world.palette.types().iter().for_each(|t| {
+            series(&|s| s.field(t.id) as f64, t.color, g);
+        });
+    }
+
+    fn inspector(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+        glyphs: &mut GlyphCache<
+            'static,
+            TextureContext<Factory, Resources, CommandBuffer>,
+            Texture<Resources>,
+        >,
+    ) {
+        let [x, y] = self.input.cursor;
+        let [w, h] = self.camera.viewport;
+        let pos = match self.camera.tile_at([x, y]) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let mut lines = vec![
+            format!("chunk: {} {}", (pos.0).0, (pos.0).1),
+            format!("tile: {}", pos.1),
+        ];
+        match self.world.tile(&pos) {
+            Some(tile) => {
+                lines.extend(tile.inspect());
+                let age = self.world.chunks[&pos.0].age[pos.1];
+                lines.push(format!("age: {}", age));
+                let mut fields = tile.quantities();
+                fields.sort();
+                fields.iter().for_each(|f| {
+                    let name = self
+                        .world
+                        .palette
+                        .get(f.0)
+                        .map_or("?".to_string(), |t| t.name.clone());
+                    lines.push(format!("field {} ({}): {}", f.0, name, f.1));
+                });
+            }
+            None => lines.push("empty chunk".to_string()),
+        }
+
+        const LINE: f64 = 18.;
+        let (px, py) = (
+            (x + 16.).min(w - 200.),
+            (y + 16.).min(h - LINE * lines.len() as f64 - 8.),
+        );
+        rectangle(
+            [0.1, 0.1, 0.1, 0.8],
+            [px, py, 200., LINE * lines.len() as f64 + 8.],
+            c.transform,
+            g,
+        );
+        lines.iter().enumerate().for_each(|(i, line)| {
+            text::Text::new_color([0.8, 0.8, 0.8, 1.], 14)
+                .draw(
+                    line,
+                    glyphs,
+                    &c.draw_state,
+                    c.transform.trans(px + 6., py + LINE * (i + 1) as f64),
+                    g,
+                )
+                .unwrap();
+        });
+    }
+
+    /// Drop-down console over the top of the window, newest line at the bottom.
+    fn draw_console(
+        &mut self,
+        c: &Context,
+        g: &mut GfxGraphics<Resources, CommandBuffer>,
+        glyphs: &mut GlyphCache<
+            'static,
+            TextureContext<Factory, Resources, CommandBuffer>,
+            Texture<Resources>,
+        >,
+    ) {
+        const LINE: f64 = 18.;
+        let [w, h] = self.camera.viewport;
+        let height = (h * 0.4).floor();
+        rectangle([0.05, 0.05, 0.05, 0.9], [0., 0., w, height], c.transform, g);
+        let prompt = format!("> {}_", self.console.line);
+        let rows = (height / LINE) as usize;
+        std::iter::once(&prompt)
+            .chain(self.console.lines().rev())
+            .take(rows)
+            .enumerate()
+            .for_each(|(i, line)| {
+                text::Text::new_color([0.8, 0.8, 0.8, 1.], 14)
+                    .draw(
+                        line,
+                        glyphs,
+                        &c.draw_state,
+                        c.transform.trans(8., height - 6. - LINE * i as f64),
+                        g,
+                    )
+                    .unwrap();
+            });
     }
 
     pub fn render(
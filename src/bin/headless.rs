@@ -0,0 +1,50 @@
+use ca04::engine::{
+    recording::{Playback, Recording},
+    world::{stats::Stats, Point},
+    World,
+};
+
+use rand::Rng;
+use std::{env, fs::File, io::BufWriter};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let generations = args.next().and_then(|a| a.parse().ok()).unwrap_or(1000u64);
+    let output = args.next().unwrap_or_else(|| "stats.csv".to_string());
+    let seeds = args.next().and_then(|a| a.parse().ok()).unwrap_or(64usize);
+    let replay = args.next();
+
+    let mut world = World::new();
+    // A recording replaces the random seeds.
+    let mut playback = replay.map(|path| {
+        let mut recording = Recording::default();
+        recording.load_recording(&path);
+        Playback::start(recording, &mut world)
+    });
+    if playback.is_none() {
+        let center = u16::MAX / 2;
+        let mut rng = rand::thread_rng();
+        for _ in 0..seeds {
+            world.put(&Point(Point(center, center), rng.gen_range(0..1024)));
+        }
+    }
+
+    let mut out = BufWriter::new(
+        File::create(&output).expect("Couldn't create stats output file."),
+    );
+    Stats::csv_header(&mut out, &world.palette).expect("Couldn't write csv.");
+    while world.generation < generations {
+        if let Some(playback) = &mut playback {
+            playback.apply(&mut world);
+        }
+        world.update();
+        if let Some(e) = world.rule.error() {
+            eprintln!("{}", e);
+        }
+        if let Some(stats) = world.history().back() {
+            stats
+                .csv_row(&mut out, &world.palette)
+                .expect("Couldn't write csv.");
+        }
+    }
+}
@@ -1,7 +1,21 @@
-use std::cmp::max;
+use std::{cmp::max, collections::HashMap, env, path::Path};
 
 use ca04::{
-    engine::{InputHandler, World},
+    engine::{
+        bookmarks::{Bookmarks, Follow},
+        console::{self, Console},
+        keymap,
+        world::{
+            lenia::Continuous,
+            multistate::Cell,
+            reaction::Chemicals,
+            render::RenderMode,
+            state::CellState,
+        },
+        Camera,
+        InputHandler,
+        World,
+    },
     App,
 };
 
@@ -21,6 +35,14 @@ use piston_window::{
 use sdl2_window::Sdl2Window;
 
 fn main() {
+    if env::args().any(|a| a == "--print-keymap") {
+        let mut input = InputHandler::new();
+        let report = input.load_keymap();
+        keymap::dump(input.bindings())
+            .iter()
+            .for_each(|line| println!("{}", line));
+        std::process::exit(if report.is_empty() { 0 } else { 1 });
+    }
     let title = "CA04";
     let opengl = OpenGL::V4_5;
     let mut window: PistonWindow<Sdl2Window> =
@@ -30,23 +52,70 @@ fn main() {
             .graphics_api(opengl)
             .build()
             .unwrap();
-    let mut app = init(title, opengl, &mut window);
+    // `--lenia` and `--reaction` run continuous worlds instead of the tile
+    // fields, and `--preset <name>` a multi-state one.
+    let preset = env::args().skip_while(|a| a != "--preset").nth(1);
+    if let Some(preset) = preset {
+        let rule = Cell::rule(&preset).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let mut world = World::with_rule(rule);
+        if let Some(palette) = Cell::palette(&preset) {
+            world.palette = palette;
+        }
+        let app = init(title, opengl, &mut window, world);
+        run(app, &mut window);
+    } else if env::args().any(|a| a == "--lenia") {
+        let rule = Continuous::rule("default").unwrap();
+        let app = init(title, opengl, &mut window, World::with_rule(rule));
+        run(app, &mut window);
+    } else if env::args().any(|a| a == "--reaction") {
+        let rule = Chemicals::rule("default").unwrap();
+        let app = init(title, opengl, &mut window, World::with_rule(rule));
+        run(app, &mut window);
+    } else {
+        let app = init(title, opengl, &mut window, World::new());
+        run(app, &mut window);
+    }
+}
+
+fn run<S: CellState>(
+    mut app: App<S>,
+    window: &mut PistonWindow<Sdl2Window>,
+) {
     let ref mut glyphs = window
         .load_font(app.assets.join("FiraSans-Regular.ttf"))
         .unwrap();
     window.set_capture_cursor(app.capture_cursor);
     window.set_max_fps(max(120, (app.ups * 4.) as u64));
     window.set_ups(app.ups as u64);
+    let script = env::args().skip_while(|a| a != "--script").nth(1);
+    let result = match script {
+        Some(script) => app.exec(&script),
+        None if Path::new(console::SCRIPT).exists() => app.exec(console::SCRIPT),
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+    let mut ups = app.ups;
     while let Some(e) = window.next() {
         window.draw_2d(&e, |c, g, device| {
             app.draw(&c, g, device, glyphs);
-            // Update glyphs before rendering.
+            // Update glyphs and chunk textures before rendering.
             glyphs.factory.encoder.flush(device);
+            app.textures.encoder.flush(device);
         });
         app.event(&e);
+        if app.ups != ups {
+            ups = app.ups;
+            window.set_max_fps(max(120, (ups * 4.) as u64));
+            window.set_ups(ups as u64);
+        }
 
         if let Some(_) = e.resize_args() {
-            app.resize(&window);
+            app.resize(window);
         }
         if let Some(_args) = e.idle_args() {
             // println!("{}", args.dt);
@@ -62,11 +131,12 @@ fn main() {
     }
 }
 
-fn init(
+fn init<S: CellState>(
     title: &str,
     opengl: OpenGL,
     window: &mut PistonWindow<Sdl2Window>,
-) -> App {
+    world: World<S>,
+) -> App<S> {
     let title = title.to_string();
     let fps = fps_counter::FPSCounter::new();
     let ups = 60.0;
@@ -75,20 +145,31 @@ fn init(
         .for_folder("assets")
         .unwrap();
     let stats = false;
+    let inspect = false;
+    let minimap = false;
+    let grid = false;
+    let borders = false;
+    let hilbert = false;
+    let paused = false;
+    let mode = RenderMode::Fields;
+    let painted = (mode, 0, 0);
     let Size {
         width: w,
         height: h,
     } = window.window.draw_size();
-    let focus = [
-        -(u16::MAX as f64 - w) / 2.,
-        -(u16::MAX as f64 - h) / 2.,
-        0.0,
-        0.0,
-    ];
-    let ar = w / h;
-    let world = World::new();
+    let camera = Camera::new(w, h);
     let mut input = InputHandler::new();
-    let size = (1., 0.);
+    let textures = window.create_texture_context();
+    let cache = HashMap::new();
+    let mut bookmarks = Bookmarks::default();
+    let bookmark = None;
+    let follow = Follow::Off;
+    let last_put = None;
+    let recording = None;
+    let playback = None;
+    let console = Console::default();
+
+    bookmarks.load_bookmarks();
 
     input.load_keymap();
 
@@ -99,13 +180,26 @@ fn init(
         ups,
         capture_cursor,
         assets,
-        focus,
-        w,
-        h,
-        ar,
         stats,
+        inspect,
+        minimap,
+        grid,
+        borders,
+        hilbert,
+        paused,
+        mode,
+        painted,
         world,
         input,
-        size,
+        camera,
+        bookmarks,
+        bookmark,
+        follow,
+        last_put,
+        recording,
+        playback,
+        console,
+        textures,
+        cache,
     }
 }
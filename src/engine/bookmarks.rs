@@ -0,0 +1,80 @@
+use crate::{
+    engine::Camera,
+    functions::{from_json, write_file},
+};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, ops::Bound};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct View {
+    pub pos:  [f64; 2],
+    pub zoom: f64,
+}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks(BTreeMap<String, View>);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Follow {
+    Off,
+    Centroid,
+    Bounds,
+}
+
+impl Bookmarks {
+    pub fn save(
+        &mut self,
+        name: &str,
+        camera: &Camera,
+    ) {
+        self.0.insert(name.to_string(), camera.view());
+        self.save_bookmarks();
+    }
+
+    pub fn get(
+        &self,
+        name: &str,
+    ) -> Option<View> {
+        self.0.get(name).copied()
+    }
+
+    /// Name of the bookmark following `name`, wrapping around to the first.
+    pub fn next(
+        &self,
+        name: Option<&str>,
+    ) -> Option<&str> {
+        let next = match name {
+            Some(name) => self
+                .0
+                .range::<str, _>((Bound::Excluded(name), Bound::Unbounded))
+                .next(),
+            None => None,
+        };
+        next.or_else(|| self.0.iter().next()).map(|(k, _)| k.as_str())
+    }
+
+    pub fn save_bookmarks(&self) {
+        serde_json::to_writer_pretty(
+            &write_file("assets/config/bookmarks.json".to_string()),
+            &self,
+        )
+        .expect("Couldn't write json to bookmarks.");
+    }
+
+    pub fn load_bookmarks(&mut self) {
+        let path = "assets/config/bookmarks.json".to_string();
+        if let Ok(b) = serde_json::from_str(&from_json(path)) {
+            *self = b;
+        };
+    }
+}
+
+impl Follow {
+    pub fn next(self) -> Self {
+        match self {
+            Follow::Off => Follow::Centroid,
+            Follow::Centroid => Follow::Bounds,
+            Follow::Bounds => Follow::Off,
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{
+    engine::{bookmarks::View, world::Point},
+    functions::{from_json, write_file},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Number of tiles along one side of the world.
+pub const WORLD: f64 = (u16::MAX as f64 + 1.) * 32.;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Motion {
+    /// Velocity gained per update while a pan key is held, in pixels.
+    pub accel:     f64,
+    /// Fraction of the velocity lost per update.
+    pub friction:  f64,
+    /// Upper bound of the velocity along each axis, in pixels per update.
+    pub max_speed: f64,
+}
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    /// World position, in tiles, of the top left corner of the viewport.
+    pub pos:      [f64; 2],
+    /// Pan velocity, in pixels per update.
+    pub vel:      [f64; 2],
+    /// Direction of keyboard panning, zeroed when no pan key is held.
+    pub thrust:   [f64; 2],
+    /// Pixels per tile.
+    pub zoom:     f64,
+    /// Zoom change still to be applied over the next updates.
+    pub zoom_vel: f64,
+    /// Screen position kept fixed while zooming, the centre when `None`.
+    pub anchor:   Option<[f64; 2]>,
+    /// Viewport size in pixels.
+    pub viewport: [f64; 2],
+    pub motion:   Motion,
+}
+
+impl Default for Motion {
+    fn default() -> Self {
+        Self {
+            accel:     1.5,
+            friction:  0.15,
+            max_speed: 40.,
+        }
+    }
+}
+impl Motion {
+    pub fn save_motion(&self) {
+        serde_json::to_writer_pretty(
+            &write_file("assets/config/camera.json".to_string()),
+            &self,
+        )
+        .expect("Couldn't write json to camera.");
+    }
+
+    pub fn load_motion(&mut self) {
+        let path = "assets/config/camera.json".to_string();
+        if let Ok(m) = serde_json::from_str(&from_json(path)) {
+            *self = m;
+        };
+    }
+}
+
+impl Camera {
+    pub fn new(
+        width: f64,
+        height: f64,
+    ) -> Self {
+        let mut camera = Self {
+            pos:      [0.; 2],
+            vel:      [0.; 2],
+            thrust:   [0.; 2],
+            zoom:     1.,
+            zoom_vel: 0.,
+            anchor:   None,
+            viewport: [width, height],
+            motion:   Motion::default(),
+        };
+        camera.motion.load_motion();
+        camera.center();
+        camera
+    }
+
+    pub fn resize(
+        &mut self,
+        width: f64,
+        height: f64,
+    ) {
+        self.viewport = [width, height];
+        self.zoom = self.zoom.max(self.min_zoom());
+    }
+
+    pub fn ar(&self) -> f64 { self.viewport[0] / self.viewport[1] }
+
+    /// Zoom at which the whole world fits in the viewport.
+    pub fn min_zoom(&self) -> f64 {
+        self.viewport[0].min(self.viewport[1]) / WORLD
+    }
+
+    /// World extent of the viewport, in tiles.
+    pub fn span(&self) -> [f64; 2] {
+        [self.viewport[0] / self.zoom, self.viewport[1] / self.zoom]
+    }
+
+    pub fn to_screen(
+        &self,
+        world: [f64; 2],
+    ) -> [f64; 2] {
+        [
+            (world[0] - self.pos[0]) * self.zoom,
+            (world[1] - self.pos[1]) * self.zoom,
+        ]
+    }
+
+    pub fn to_world(
+        &self,
+        screen: [f64; 2],
+    ) -> [f64; 2] {
+        [
+            screen[0] / self.zoom + self.pos[0],
+            screen[1] / self.zoom + self.pos[1],
+        ]
+    }
+
+    /// Chunk and tile index under a screen position, or `None` outside the
+    /// world.
+    pub fn tile_at(
+        &self,
+        screen: [f64; 2],
+    ) -> Option<Point<Point<u16>, usize>> {
+        let [x, y] = self.to_world(screen);
+        if x < 0. || y < 0. || x >= WORLD || y >= WORLD {
+            return None;
+        }
+        let (x, y) = (x.floor() as u32, y.floor() as u32);
+        Some(Point(
+            Point((x / 32) as u16, (y / 32) as u16),
+            ((y % 32) * 32 + x % 32) as usize,
+        ))
+    }
+
+    /// Inclusive range of chunks intersecting the viewport, or `None` when the
+    /// viewport lies entirely outside the world.
+    pub fn visible(&self) -> Option<(Point<u16>, Point<u16>)> {
+        let span = self.span();
+        let end = [self.pos[0] + span[0], self.pos[1] + span[1]];
+        if end[0] <= 0. ||
+            end[1] <= 0. ||
+            self.pos[0] >= WORLD ||
+            self.pos[1] >= WORLD
+        {
+            return None;
+        }
+        let first = |v: f64| (v / 32.).floor().max(0.) as u16;
+        let last = |v: f64| {
+            ((v / 32.).ceil() - 1.).max(0.).min(u16::MAX as f64) as u16
+        };
+        Some((
+            Point(first(self.pos[0]), first(self.pos[1])),
+            Point(last(end[0]), last(end[1])),
+        ))
+    }
+
+    /// Sets the direction keyboard panning accelerates in.
+    pub fn pan(
+        &mut self,
+        dx: f64,
+        dy: f64,
+    ) {
+        self.thrust = [dx, dy];
+    }
+
+    /// Moves the view along with a cursor dragged by `dx`, `dy` pixels.
+    pub fn drag(
+        &mut self,
+        dx: f64,
+        dy: f64,
+    ) {
+        self.pos[0] -= dx / self.zoom;
+        self.pos[1] -= dy / self.zoom;
+        self.vel = [0.; 2];
+    }
+
+    /// Starts zooming, keeping the world point under `anchor` in place.
+    pub fn zoom_by(
+        &mut self,
+        scroll: f64,
+        anchor: [f64; 2],
+    ) {
+        const FACTOR: f64 = 1.3;
+        self.zoom_vel = scroll * self.zoom * FACTOR;
+        self.anchor = Some(anchor);
+    }
+
+    /// Moves the camera so that the given world position is centred.
+    pub fn look_at(
+        &mut self,
+        world: [f64; 2],
+    ) {
+        let span = self.span();
+        self.pos = [world[0] - span[0] / 2., world[1] - span[1] / 2.];
+    }
+
+    pub fn center(&mut self) { self.look_at([WORLD / 2., WORLD / 2.]); }
+
+    pub fn view(&self) -> View {
+        View {
+            pos:  self.pos,
+            zoom: self.zoom,
+        }
+    }
+
+    pub fn set_view(
+        &mut self,
+        view: View,
+    ) {
+        self.pos = view.pos;
+        self.zoom = view.zoom.max(self.min_zoom());
+        self.vel = [0.; 2];
+        self.zoom_vel = 0.;
+    }
+
+    /// Eases the view towards centring `target`, and towards `zoom` if given.
+    pub fn track(
+        &mut self,
+        target: [f64; 2],
+        zoom: Option<f64>,
+    ) {
+        const EASE: f64 = 0.1;
+        if let Some(zoom) = zoom {
+            let zoom = zoom.max(self.min_zoom());
+            self.zoom += (zoom - self.zoom) * EASE;
+        }
+        let span = self.span();
+        let center = [self.pos[0] + span[0] / 2., self.pos[1] + span[1] / 2.];
+        self.look_at([
+            center[0] + (target[0] - center[0]) * EASE,
+            center[1] + (target[1] - center[1]) * EASE,
+        ]);
+    }
+
+    pub fn update(
+        &mut self,
+        ups: f64,
+    ) {
+        let anchor = self
+            .anchor
+            .unwrap_or([self.viewport[0] / 2., self.viewport[1] / 2.]);
+        let fixed = self.to_world(anchor);
+        let step = self.zoom_vel / ups;
+        self.zoom = (self.zoom + step).max(self.min_zoom());
+        self.zoom_vel = if step.abs() > self.zoom * ups.recip() {
+            self.zoom_vel - step
+        } else {
+            self.anchor = None;
+            0.
+        };
+        self.pos = [
+            fixed[0] - anchor[0] / self.zoom,
+            fixed[1] - anchor[1] / self.zoom,
+        ];
+
+        let Motion {
+            accel,
+            friction,
+            max_speed,
+        } = self.motion;
+        for i in 0..2 {
+            let v = (self.vel[i] + self.thrust[i] * accel) * (1. - friction);
+            self.vel[i] = if v.abs() < 0.01 {
+                0.
+            } else {
+                v.max(-max_speed).min(max_speed)
+            };
+            self.pos[i] += self.vel[i] / self.zoom;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(
+        pos: [f64; 2],
+        zoom: f64,
+    ) -> Camera {
+        let mut camera = Camera::new(800., 600.);
+        camera.pos = pos;
+        camera.zoom = zoom;
+        camera
+    }
+
+    #[test]
+    fn tile_at_corners() {
+        let c = camera([0.; 2], 1.);
+        assert_eq!(c.tile_at([0., 0.]), Some(Point(Point(0, 0), 0)));
+        assert_eq!(c.tile_at([33.5, 1.5]), Some(Point(Point(1, 0), 33)));
+        let edge = WORLD - 1e-6;
+        assert_eq!(
+            c.tile_at([edge, edge]),
+            Some(Point(Point(u16::MAX, u16::MAX), 32 * 32 - 1))
+        );
+    }
+
+    #[test]
+    fn tile_at_outside() {
+        let c = camera([0.; 2], 1.);
+        assert_eq!(c.tile_at([-1e-6, 0.]), None);
+        assert_eq!(c.tile_at([0., -1e-6]), None);
+        assert_eq!(c.tile_at([WORLD, 0.]), None);
+        assert_eq!(c.tile_at([0., WORLD]), None);
+    }
+
+    #[test]
+    fn visible_from_origin() {
+        let c = camera([0.; 2], 1.);
+        assert_eq!(c.visible(), Some((Point(0, 0), Point(24, 18))));
+    }
+
+    #[test]
+    fn visible_past_world() {
+        let c = camera([WORLD - 100.; 2], 1.);
+        assert_eq!(
+            c.visible(),
+            Some((Point(65532, 65532), Point(u16::MAX, u16::MAX)))
+        );
+        let c = camera([-WORLD; 2], camera([0.; 2], 1.).min_zoom() / 4.);
+        assert_eq!(c.visible(), Some((Point(0, 0), Point(u16::MAX, u16::MAX))));
+    }
+
+    #[test]
+    fn visible_partly_off_each_edge() {
+        let left = camera([-100., 0.], 1.);
+        assert_eq!(left.visible(), Some((Point(0, 0), Point(21, 18))));
+        let top = camera([0., -50.], 1.);
+        assert_eq!(top.visible(), Some((Point(0, 0), Point(24, 17))));
+        let right = camera([WORLD - 400., 0.], 1.);
+        assert_eq!(
+            right.visible(),
+            Some((Point(65523, 0), Point(u16::MAX, 18)))
+        );
+        let bottom = camera([0., WORLD - 300.], 1.);
+        assert_eq!(
+            bottom.visible(),
+            Some((Point(0, 65526), Point(24, u16::MAX)))
+        );
+    }
+
+    #[test]
+    fn visible_off_world() {
+        assert_eq!(camera([-800., 0.], 1.).visible(), None);
+        assert_eq!(camera([0., -600.], 1.).visible(), None);
+        assert_eq!(camera([WORLD, 0.], 1.).visible(), None);
+        assert_eq!(camera([0., WORLD], 1.).visible(), None);
+    }
+
+    #[test]
+    fn screen_world_round_trip() {
+        let screens = [[0., 0.], [400., 300.], [799.5, 599.5], [-10., 1e3]];
+        for &(pos, zoom) in &[
+            ([0., 0.], 1.),
+            ([-123.25, 4567.5], 0.37),
+            ([WORLD / 2., WORLD - 1.], 48.),
+            ([WORLD / 3., 17.], 1e6),
+        ] {
+            let c = camera(pos, zoom);
+            for &s in &screens {
+                let back = c.to_screen(c.to_world(s));
+                for i in 0..2 {
+                    assert!((back[i] - s[i]).abs() < 1e-3, "{:?}", (pos, s));
+                }
+                let w = c.to_world(s);
+                let again = c.to_world(c.to_screen(w));
+                for i in 0..2 {
+                    assert!((again[i] - w[i]).abs() < 1e-6, "{:?}", (pos, w));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn min_zoom_fits_world() {
+        let mut c = camera([0.; 2], 1.);
+        assert_eq!(c.min_zoom(), 600. / WORLD);
+        c.zoom = c.min_zoom();
+        let span = c.span();
+        assert!(span[0] >= WORLD && span[1] >= WORLD);
+        c.center();
+        assert_eq!(c.visible(), Some((Point(0, 0), Point(u16::MAX, u16::MAX))));
+
+        c.zoom = 0.;
+        c.resize(300., 900.);
+        assert_eq!(c.zoom, 300. / WORLD);
+    }
+
+    #[test]
+    fn huge_zoom() {
+        let mut c = camera([0.; 2], 1.);
+        c.zoom = 1e9;
+        c.look_at([WORLD / 2. + 0.5, WORLD / 2. + 0.5]);
+        let tile = Point(Point(32768, 32768), 0);
+        assert_eq!(c.tile_at([0., 0.]), Some(tile));
+        assert_eq!(c.tile_at([799., 599.]), Some(tile));
+        assert_eq!(
+            c.visible(),
+            Some((Point(32768, 32768), Point(32768, 32768)))
+        );
+        c.zoom = f64::MAX;
+        assert_eq!(c.tile_at([400., 300.]), Some(tile));
+    }
+}
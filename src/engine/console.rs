@@ -0,0 +1,144 @@
+use crate::engine::world::script;
+
+use piston_window::{Button, Event, Key, PressEvent, TextEvent};
+use std::collections::VecDeque;
+
+/// Script run at startup, if it exists.
+pub const SCRIPT: &str = "assets/config/startup.txt";
+/// Number of log lines kept.
+const LOG: usize = 64;
+
+#[derive(Clone, Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    /// Text typed so far.
+    pub line: String,
+    log:      VecDeque<String>,
+}
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Run the world for a number of generations.
+    Step(u64),
+    /// Stamp a pattern with its top left corner at a world position, in
+    /// tiles, or replace the world with a saved one when no position is given.
+    Load {
+        path: String,
+        at:   Option<[i64; 2]>,
+    },
+    /// Replace the world's rule, by a name `CellState::rule` understands.
+    Rule(String),
+    /// Place an ant with a turmite rule at a world position, in tiles, or at
+    /// the centre of the view when no position is given.
+    Ant {
+        rule: String,
+        at:   Option<[i64; 2]>,
+    },
+    ClearAnts,
+    /// Centre the view on a world position, in tiles.
+    Goto([f64; 2]),
+    Clear,
+    Save(String),
+    Set(String, f64),
+    Pause,
+    /// Run every line of a script file.
+    Exec(String),
+    LoadKeymap,
+    SaveKeymap,
+}
+
+impl Console {
+    pub fn toggle(&mut self) { self.open = !self.open; }
+
+    pub fn log(
+        &mut self,
+        line: String,
+    ) {
+        if self.log.len() == LOG {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    /// Logged lines, oldest first.
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.log.iter()
+    }
+
+    /// Edits the typed line, returning it when submitted.
+    pub fn input(
+        &mut self,
+        e: &Event,
+    ) -> Option<String> {
+        if let Some(text) = e.text_args() {
+            // The toggle key shouldn't end up in the line it opened.
+            let typed = text.chars().filter(|&c| c != '`' && !c.is_control());
+            self.line.extend(typed);
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Backspace)) => {
+                self.line.pop();
+                None
+            }
+            Some(Button::Keyboard(Key::Return)) => {
+                Some(std::mem::take(&mut self.line))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Command {
+    /// Parses one console or script line. Blank lines and `#` comments parse
+    /// to `None`.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let command = match words.as_slice() {
+            [] => return Ok(None),
+            [w, ..] if w.starts_with('#') => return Ok(None),
+            ["step"] => Command::Step(1),
+            ["step", n] => Command::Step(number(n)?),
+            ["load", path] => Command::Load {
+                path: path.to_string(),
+                at:   None,
+            },
+            ["load", path, "at", x, y] => Command::Load {
+                path: path.to_string(),
+                at:   Some([number(x)?, number(y)?]),
+            },
+            ["rule", "script"] => {
+                Command::Rule(format!("script:{}", script::PATH))
+            }
+            ["rule", "script", path] => Command::Rule(format!("script:{}", path)),
+            ["rule", rule] => Command::Rule(rule.to_string()),
+            ["ant", "clear"] => Command::ClearAnts,
+            ["ant", rule] => Command::Ant {
+                rule: rule.to_string(),
+                at:   None,
+            },
+            ["ant", rule, "at", x, y] => Command::Ant {
+                rule: rule.to_string(),
+                at:   Some([number(x)?, number(y)?]),
+            },
+            ["goto", "chunk", x, y] => Command::Goto([
+                number::<f64>(x)? * 32. + 16.,
+                number::<f64>(y)? * 32. + 16.,
+            ]),
+            ["goto", x, y] => Command::Goto([number(x)?, number(y)?]),
+            ["clear"] => Command::Clear,
+            ["save", path] => Command::Save(path.to_string()),
+            ["set", name, value] => {
+                Command::Set(name.to_string(), number(value)?)
+            }
+            ["pause"] => Command::Pause,
+            ["exec", path] => Command::Exec(path.to_string()),
+            ["keymap", "load"] => Command::LoadKeymap,
+            ["keymap", "save"] => Command::SaveKeymap,
+            _ => return Err(format!("unknown command: {}", line.trim())),
+        };
+        Ok(Some(command))
+    }
+}
+
+fn number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("not a number: {}", word))
+}
@@ -1,11 +1,12 @@
-use crate::{
-    engine::input::{Action::*, MouseB::*, MouseM::*},
-    functions::{from_json, read_file},
+use crate::engine::{
+    input::{Action::*, MouseM::*},
+    keymap::{self, Bindings, Report, Trigger},
 };
 
 use piston_window::{
     Button,
     Event,
+    Key,
     MouseButton,
     MouseCursorEvent,
     MouseScrollEvent,
@@ -13,32 +14,55 @@ use piston_window::{
     ReleaseEvent,
 };
 use serde::{Deserialize, Serialize};
-use serde_with::{json::JsonString, serde_as};
 use std::{
-    collections::{btree_map, BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap},
     time::{Duration, Instant},
 };
 
 #[derive(Debug, Clone)]
 pub struct InputHandler {
-    mouse:      BTreeMap<Button, MouseB>,
     motion:     [Option<MouseM>; 2],
-    down:       BTreeSet<Button>,
-    last:       BTreeSet<Button>,
-    repeat:     bool,
+    down:       BTreeSet<Input>,
+    fresh:      bool,
     drag:       bool,
-    delay:      Duration,
-    time:       Instant,
-    keymap:     HashMap<BTreeSet<Button>, Action>,
+    since:      Instant,
+    next:       Option<Instant>,
+    fired:      bool,
+    keymap:     Bindings,
     pub cursor: [f64; 2],
     scroll:     bool,
 }
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A single part of a chord. Keyboard and mouse buttons serialize the same
+/// way as piston's `Button`, so keymaps stay compatible.
+#[derive(
+    Eq, Ord, PartialEq, PartialOrd, Hash, Copy, Clone, Debug, Serialize, Deserialize,
+)]
+pub enum Input {
+    Keyboard(Key),
+    Mouse(MouseButton),
+    Scroll(Wheel),
+}
+#[derive(
+    Eq, Ord, PartialEq, PartialOrd, Hash, Copy, Clone, Debug, Serialize, Deserialize,
+)]
+pub enum Wheel {
+    Up,
+    Down,
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     Pass,
     Exit,
     Stats,
+    Inspect,
+    Clear,
     ResetZoom,
+    ResetOrigin,
+    Pause,
+    Step,
+    Record,
+    Replay,
+    Console,
     N,
     NE,
     E,
@@ -47,12 +71,30 @@ pub enum Action {
     SW,
     W,
     NW,
-}
-#[derive(Copy, Clone, Debug)]
-pub enum MouseB {
-    LMB(f64, f64),
-    RMB(f64, f64),
-    MMB(f64, f64),
+    Select(usize),
+    SaveBookmark(String),
+    Bookmark(String),
+    NextBookmark,
+    FollowMode,
+    Minimap,
+    NextMode,
+    NextPreset,
+    Rule(String),
+    Grid,
+    Borders,
+    Hilbert,
+    Put,
+    Remove,
+    Pick,
+    PlaceAnt,
+    PlaceTurmite(String),
+    ClearAnts,
+    Pan,
+    LookAt,
+    ZoomIn,
+    ZoomOut,
+    NextField,
+    PrevField,
 }
 #[derive(Copy, Clone, Debug)]
 pub enum MouseM {
@@ -62,133 +104,166 @@ pub enum MouseM {
 
 impl InputHandler {
     pub fn new() -> Self {
-        let mouse = BTreeMap::new();
         let motion = [None; 2];
         let down = BTreeSet::new();
-        let last = BTreeSet::new();
-        let repeat = false;
+        let fresh = false;
         let drag = false;
-        let delay = Duration::new(0, 250_000_000);
-        let time = Instant::now();
+        let since = Instant::now();
+        let next = None;
+        let fired = false;
         let keymap = HashMap::new();
         let cursor = [0.; 2];
         let scroll = false;
         Self {
-            mouse,
             motion,
             down,
-            last,
-            repeat,
+            fresh,
             drag,
-            delay,
-            time,
+            since,
+            next,
+            fired,
             keymap,
             cursor,
             scroll,
         }
     }
 
+    /// Updates the input state and returns the action whose trigger fired
+    /// during this event, if any.
     pub fn event(
         &mut self,
         e: &Event,
-    ) -> &Action {
+    ) -> Action {
+        let before = self.down.clone();
         if self.scroll {
             self.motion[0] = None;
+            self.down.retain(|i| !matches!(i, Input::Scroll(_)));
             self.scroll = false;
         }
-        match e.mouse_cursor(|xy| xy) {
-            Some(pos) => {
-                self.cursor = pos;
-                if !self.mouse.is_empty() {
-                    self.drag = true;
-                }
-            }
-            None => {
-                if !self.mouse.is_empty() {
-                    self.mouse.clear();
-                    self.drag = false;
-                }
+        self.fresh = false;
+        self.motion[1] = None;
+        if let Some(pos) = e.mouse_cursor(|xy| xy) {
+            if self.mouse_held() {
+                let [x, y] = self.cursor;
+                self.motion[1] = Some(Drag(x, y, pos[0], pos[1]));
+                self.drag = true;
             }
-        };
+            self.cursor = pos;
+        }
         e.mouse_scroll(|d| {
-            self.motion[0] = Some(Scroll(d[1]));
-            self.scroll = true;
+            if d[1] != 0. {
+                let wheel = if d[1] > 0. { Wheel::Up } else { Wheel::Down };
+                self.motion[0] = Some(Scroll(d[1]));
+                self.down.insert(Input::Scroll(wheel));
+                self.scroll = true;
+                self.fresh = true;
+            }
         });
-        if let Some(button) = e.press_args() {
-            if let Button::Keyboard(_) = button {
-                self.last.clear();
-                self.last.insert(button);
-                self.time = Instant::now();
-                self.down.insert(button);
+        if let Some(input) = e.press_args().and_then(Input::from_button) {
+            if let Input::Mouse(_) = input {
+                self.drag = false;
             }
-            if let Button::Mouse(mouse_button) = button {
-                if mouse_button == MouseButton::Left {
-                    self.mouse
-                        .insert(button, LMB(self.cursor[0], self.cursor[1]));
-                }
-                if mouse_button == MouseButton::Right {
-                    self.mouse
-                        .insert(button, RMB(self.cursor[0], self.cursor[1]));
-                }
-                if mouse_button == MouseButton::Middle {
-                    self.mouse
-                        .insert(button, MMB(self.cursor[0], self.cursor[1]));
-                    // world.remove(world.grid.get_pos(cursor[0],
-                    // cursor[1]));
+            self.fresh = self.down.insert(input);
+        }
+        if let Some(input) = e.release_args().and_then(Input::from_button) {
+            self.down.remove(&input);
+        }
+
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+        let mut action = Pass;
+        if self.down != before {
+            if let Some(b) = self.keymap.get(&before) {
+                match &b.trigger {
+                    Trigger::Release => action = b.action.clone(),
+                    Trigger::LongPress { .. } if !self.fired => {
+                        action = b.action.clone()
+                    }
+                    _ => {}
                 }
             }
-        }
-        if let Some(button) = e.release_args() {
-            if let Button::Keyboard(_) = button {
-                if self.down.contains(&button) {
-                    self.down.remove(&button);
-                    self.last.clear();
-                    self.repeat = false;
+            // Chords reached by releasing part of a larger chord only resume
+            // held actions, so releasing a combo doesn't fire toggles.
+            let pressed = self.down.difference(&before).next().is_some();
+            self.since = now;
+            self.next = None;
+            self.fired = false;
+            if let Some(b) = self.keymap.get(&self.down) {
+                match &b.trigger {
+                    Trigger::Press if pressed && action == Pass => {
+                        action = b.action.clone()
+                    }
+                    Trigger::Hold { delay, .. } => {
+                        if action == Pass {
+                            action = b.action.clone();
+                        }
+                        self.next = Some(now + ms(*delay));
+                    }
+                    _ => {}
                 }
             }
-            if let Button::Mouse(mouse_button) = button {
-                self.mouse.remove(&button);
-                if mouse_button == MouseButton::Left {}
-                if mouse_button == MouseButton::Right {}
-                if mouse_button == MouseButton::Middle {}
+        } else if let Some(b) = self.keymap.get(&self.down) {
+            match &b.trigger {
+                Trigger::Hold { rate, .. } => {
+                    if self.next.map_or(false, |next| now >= next) {
+                        action = b.action.clone();
+                        self.next = Some(now + ms(*rate));
+                    }
+                }
+                Trigger::LongPress { after, action: alt } => {
+                    if !self.fired && now - self.since >= ms(*after) {
+                        action = alt.clone();
+                        self.fired = true;
+                    }
+                }
+                _ => {}
             }
         }
+        action
+    }
 
-        if !self.down.is_empty() {}
+    pub fn motion(&self) -> &[Option<MouseM>] { &self.motion }
 
-        self.repeat = self.time.elapsed() >= self.delay;
+    /// Whether the cursor moved while a mouse button was held since that
+    /// button was pressed.
+    pub fn dragging(&self) -> bool { self.drag && self.mouse_held() }
 
-        if self.keymap.contains_key(&self.down) {
-            self.keymap.get(&self.down).unwrap()
-        } else {
-            &Pass
-        }
+    fn mouse_held(&self) -> bool {
+        self.down.iter().any(|i| matches!(i, Input::Mouse(_)))
     }
 
-    pub fn mouse(&self) -> btree_map::Values<'_, Button, MouseB> {
-        self.mouse.values()
+    pub fn fresh(&self) -> bool { self.fresh }
+
+    pub fn held(
+        &self,
+        key: Key,
+    ) -> bool {
+        self.down.contains(&Input::Keyboard(key))
     }
 
-    pub fn motion(&self) -> &[Option<MouseM>] { &self.motion }
+    pub fn bindings(&self) -> &Bindings { &self.keymap }
 
-    pub fn repeat(&self) -> bool { self.repeat }
+    pub fn save_keymap(&self) { keymap::save(&self.keymap); }
 
-    pub fn save_keymap(&self) {
-        serde_json::to_writer(
-            &read_file("assets/config/keymap.json".to_string()),
-            &KeyMap(self.keymap.clone()),
-        )
-        .expect("Couldn't write json to keymap.");
+    /// Loads the default keymap merged with the user's overrides, printing
+    /// any invalid or conflicting entries.
+    pub fn load_keymap(&mut self) -> Report {
+        let mut report = Report::default();
+        self.keymap = keymap::load(&mut report);
+        report.print();
+        report
     }
+}
 
-    pub fn load_keymap(&mut self) {
-        let path = "assets/config/keymap.json".to_string();
-        if let Ok(KeyMap(k)) = serde_json::from_str(&from_json(path)) {
-            self.keymap = k;
-        };
+impl Input {
+    pub fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::Keyboard(key) => Some(Input::Keyboard(key)),
+            Button::Mouse(mouse_button) => Some(Input::Mouse(mouse_button)),
+            _ => None,
+        }
     }
 }
-
 impl PartialEq for MouseM {
     fn eq(
         &self,
@@ -203,8 +278,3 @@ impl PartialEq for MouseM {
         f(self) == f(&other)
     }
 }
-#[serde_as]
-#[derive(Serialize, Deserialize)]
-struct KeyMap(
-    #[serde_as(as = "HashMap<JsonString, _>")] HashMap<BTreeSet<Button>, Action>,
-);
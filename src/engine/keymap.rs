@@ -0,0 +1,326 @@
+use crate::{
+    engine::input::{Action, Input},
+    functions::{read_file, write_file},
+};
+
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+use serde_json::Value;
+use serde_with::{json::JsonString, serde_as};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    io::Read,
+};
+
+pub type Bindings = HashMap<BTreeSet<Input>, Binding>;
+
+/// Bindings compiled into the binary, overridden by `PATH`.
+const DEFAULT: &str = include_str!("../../assets/config/default_keymap.json");
+const PATH: &str = "assets/config/keymap.json";
+
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct KeyMap(#[serde_as(as = "HashMap<JsonString, _>")] Bindings);
+/// The entries of a keymap object in the order written, keeping keys that
+/// appear more than once, which a map would silently drop.
+struct Entries(Vec<(String, Value)>);
+
+/// When a bound chord emits its action. Times are in milliseconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// Once, when the chord is pressed.
+    Press,
+    /// Once, when the chord is released.
+    Release,
+    /// When the chord is pressed, again after `delay` and then every `rate`
+    /// for as long as it is held. A `rate` of 0 repeats on every event.
+    Hold { delay: u64, rate: u64 },
+    /// The bound action on release if held for less than `after`, otherwise
+    /// `action` once as soon as `after` has passed.
+    LongPress { after: u64, action: Action },
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BindingDef")]
+pub struct Binding {
+    pub action:  Action,
+    pub trigger: Trigger,
+}
+/// A binding as written in the keymap: either just the action, using its
+/// default trigger, or the action with an explicit trigger.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingDef {
+    Plain(Action),
+    Full {
+        action:  Action,
+        trigger: Option<Trigger>,
+    },
+}
+
+impl From<BindingDef> for Binding {
+    fn from(def: BindingDef) -> Self {
+        let (action, trigger) = match def {
+            BindingDef::Plain(action) => (action, None),
+            BindingDef::Full { action, trigger } => (action, trigger),
+        };
+        let trigger = trigger.unwrap_or_else(|| action.default_trigger());
+        Self { action, trigger }
+    }
+}
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = Entries;
+
+            fn expecting(
+                &self,
+                f: &mut fmt::Formatter,
+            ) -> fmt::Result {
+                write!(f, "a keymap object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Entries, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Entries(entries))
+            }
+        }
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+impl Action {
+    /// Continuous actions repeat on every event while held, everything else
+    /// fires once per press.
+    pub fn default_trigger(&self) -> Trigger {
+        use Action::*;
+        match self {
+            N | NE | E | SE | S | SW | W | NW | Put | Remove | Pan => {
+                Trigger::Hold { delay: 0, rate: 0 }
+            }
+            Step => Trigger::Hold {
+                delay: 400,
+                rate:  100,
+            },
+            _ => Trigger::Press,
+        }
+    }
+}
+
+/// Problems found while loading the keymap. Loading never fails; offending
+/// entries are skipped and listed here instead.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub invalid:   Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.invalid.is_empty() && self.conflicts.is_empty()
+    }
+
+    pub fn print(&self) {
+        for i in &self.invalid {
+            eprintln!("keymap: invalid entry {}", i);
+        }
+        for c in &self.conflicts {
+            eprintln!("keymap: conflicting binding {}", c);
+        }
+    }
+}
+
+/// Parses a keymap json object, skipping and reporting entries whose chord or
+/// action don't parse and chords bound more than once, of which the first
+/// binding is kept.
+pub fn parse(
+    json: &str,
+    source: &str,
+    report: &mut Report,
+) -> Vec<(BTreeSet<Input>, Binding)> {
+    let entries = match serde_json::from_str::<Entries>(json) {
+        Ok(Entries(entries)) => entries,
+        Err(e) => {
+            report.invalid.push(format!("{}: {}", source, e));
+            return Vec::new();
+        }
+    };
+    let mut parsed: Vec<(BTreeSet<Input>, Binding)> = Vec::new();
+    for (k, v) in entries {
+        let chord = match serde_json::from_str::<BTreeSet<Input>>(&k) {
+            Ok(chord) if !chord.is_empty() => chord,
+            Ok(_) => {
+                report.invalid.push(format!("{}: {}: empty chord", source, k));
+                continue;
+            }
+            Err(e) => {
+                report.invalid.push(format!("{}: {}: {}", source, k, e));
+                continue;
+            }
+        };
+        let binding = match serde_json::from_value::<Binding>(v.clone()) {
+            Ok(binding) => binding,
+            Err(e) => {
+                report.invalid.push(format!("{}: {} => {}: {}", source, k, v, e));
+                continue;
+            }
+        };
+        match parsed.iter().find(|(c, _)| c == &chord) {
+            Some((_, b)) => report.conflicts.push(format!(
+                "{}: {} => {:?} and {:?}",
+                source,
+                describe(&chord),
+                b.action,
+                binding.action
+            )),
+            None => parsed.push((chord, binding)),
+        }
+    }
+    parsed
+}
+
+/// Built-in bindings merged with the user's overrides. Binding a chord to
+/// `Pass` removes it.
+pub fn load(report: &mut Report) -> Bindings {
+    let mut bindings = parse(DEFAULT, "defaults", report)
+        .into_iter()
+        .collect::<Bindings>();
+    if let Ok(mut file) = read_file(PATH.to_string()) {
+        let mut json = String::new();
+        match file.read_to_string(&mut json) {
+            Ok(_) => {
+                for (chord, binding) in parse(&json, PATH, report) {
+                    match binding.action {
+                        Action::Pass => bindings.remove(&chord),
+                        _ => bindings.insert(chord, binding),
+                    };
+                }
+            }
+            Err(e) => report.invalid.push(format!("{}: {}", PATH, e)),
+        }
+    }
+    bindings
+}
+
+/// Bindings that differ from the built-in ones, with removed built-in chords
+/// bound to `Pass`, so that loading them over the defaults gives `bindings`.
+pub fn overrides(bindings: &Bindings) -> Bindings {
+    let defaults = parse(DEFAULT, "defaults", &mut Report::default())
+        .into_iter()
+        .collect::<Bindings>();
+    let mut overrides = bindings
+        .iter()
+        .filter(|(chord, b)| defaults.get(chord) != Some(b))
+        .map(|(chord, b)| (chord.clone(), b.clone()))
+        .collect::<Bindings>();
+    defaults
+        .into_iter()
+        .filter(|(chord, _)| !bindings.contains_key(chord))
+        .for_each(|(chord, _)| {
+            let trigger = Action::Pass.default_trigger();
+            overrides.insert(chord, Binding {
+                action: Action::Pass,
+                trigger,
+            });
+        });
+    overrides
+}
+
+/// Writes the user's overrides of the built-in bindings to `PATH`.
+pub fn save(bindings: &Bindings) {
+    serde_json::to_writer_pretty(
+        &write_file(PATH.to_string()),
+        &KeyMap(overrides(bindings)),
+    )
+    .expect("Couldn't write json to keymap.");
+}
+
+pub fn describe(chord: &BTreeSet<Input>) -> String {
+    chord
+        .iter()
+        .map(|i| match i {
+            Input::Keyboard(k) => format!("{:?}", k),
+            Input::Mouse(m) => format!("Mouse{:?}", m),
+            Input::Scroll(w) => format!("Scroll{:?}", w),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// One line per binding, sorted by chord.
+pub fn dump(bindings: &Bindings) -> Vec<String> {
+    let mut lines = bindings
+        .iter()
+        .map(|(chord, b)| {
+            format!("{:<16} {:?} {:?}", describe(chord), b.action, b.trigger)
+        })
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_window::{Key, MouseButton};
+
+    fn chord(inputs: &[Input]) -> BTreeSet<Input> {
+        inputs.iter().copied().collect()
+    }
+
+    fn defaults() -> Bindings {
+        let mut report = Report::default();
+        let bindings = parse(DEFAULT, "defaults", &mut report);
+        assert!(report.is_empty(), "{:?}", report);
+        bindings.into_iter().collect()
+    }
+
+    #[test]
+    fn duplicate_chords_conflict() {
+        let json = r#"{
+            "[{\"Keyboard\":\"A\"}]": "W",
+            "[{\"Keyboard\":\"A\"}]": "Clear",
+            "[{\"Keyboard\":\"B\"}]": "Pause",
+            "[{\"Keyboard\":\"B\"}]": "Pause"
+        }"#;
+        let mut report = Report::default();
+        let parsed = parse(json, "test", &mut report);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].1.action, Action::W);
+        assert_eq!(report.conflicts.len(), 2);
+        assert!(report.invalid.is_empty());
+    }
+
+    #[test]
+    fn overrides_hold_only_changes() {
+        let mut bindings = defaults();
+        assert!(overrides(&bindings).is_empty());
+
+        let clear = chord(&[Input::Keyboard(Key::C)]);
+        let put = chord(&[Input::Mouse(MouseButton::Left)]);
+        let new = chord(&[Input::Keyboard(Key::F12)]);
+        bindings.remove(&clear);
+        bindings.insert(new.clone(), Binding {
+            action:  Action::Clear,
+            trigger: Trigger::Press,
+        });
+        bindings.get_mut(&put).unwrap().trigger = Trigger::Press;
+        let overrides = overrides(&bindings);
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(overrides[&clear].action, Action::Pass);
+        assert_eq!(overrides[&new].action, Action::Clear);
+        assert_eq!(overrides[&put].trigger, Trigger::Press);
+    }
+}
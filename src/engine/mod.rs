@@ -1,5 +1,11 @@
+pub mod bookmarks;
+pub mod camera;
+pub mod console;
 pub mod input;
+pub mod keymap;
+pub mod recording;
 pub mod world;
 
+pub use camera::Camera;
 pub use input::InputHandler;
 pub use world::World;
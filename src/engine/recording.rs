@@ -0,0 +1,188 @@
+use crate::{
+    engine::{
+        input::Action::{self, *},
+        world::{
+            ant::{Ant, Turmite},
+            state::CellState,
+            tile::Tile,
+            Point,
+            Snapshot,
+        },
+        World,
+    },
+    functions::{from_json, write_file},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const PATH: &str = "assets/recording.json";
+
+/// A world edit made at `generation`, with the tile it was made on for mouse
+/// edits.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub generation: u64,
+    pub action:     Action,
+    pub pos:        Option<Point<Point<u16>, usize>>,
+}
+/// The edits made to a world since recording started, and the world as it
+/// was then. Only actions that change the world are kept, so camera and
+/// overlay input don't bloat recordings.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recording<S = Tile> {
+    /// Starting world, or none to start from a cleared one.
+    #[serde(default)]
+    world:   Option<Snapshot<S>>,
+    entries: Vec<Entry>,
+}
+/// Replays a recording against a world as it reaches each entry's generation.
+#[derive(Clone, Debug)]
+pub struct Playback {
+    entries: Vec<Entry>,
+    next:    usize,
+}
+
+impl<S: CellState> Recording<S> {
+    /// Starts recording from a snapshot of the world and the active rule and
+    /// field, so the recording replays the same on any world.
+    pub fn start(world: &World<S>) -> Self {
+        let mut recording = Self {
+            world:   Some(world.snapshot()),
+            entries: Vec::new(),
+        };
+        recording.record(world, Rule(world.rule.name()), None);
+        recording.record(world, Select(world.palette.selected()), None);
+        recording
+    }
+
+    pub fn record(
+        &mut self,
+        world: &World<S>,
+        action: Action,
+        pos: Option<Point<Point<u16>, usize>>,
+    ) {
+        if edits(&action) {
+            self.entries.push(Entry {
+                generation: world.generation,
+                action,
+                pos,
+            });
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] { &self.entries }
+
+    pub fn save_recording(
+        &self,
+        path: &str,
+    ) {
+        serde_json::to_writer_pretty(&write_file(path.to_string()), &self)
+            .expect("Couldn't write json to recording.");
+    }
+
+    pub fn load_recording(
+        &mut self,
+        path: &str,
+    ) {
+        if let Ok(r) = serde_json::from_str(&from_json(path.to_string())) {
+            *self = r;
+        };
+    }
+}
+
+impl Playback {
+    /// Restores the world the recording started from, or clears the world
+    /// for recordings without one, and starts replaying `recording` on it.
+    pub fn start<S: CellState>(
+        recording: Recording<S>,
+        world: &mut World<S>,
+    ) -> Self {
+        match recording.world {
+            Some(snapshot) => {
+                world.restore(snapshot);
+            }
+            None => world.end(),
+        }
+        Self {
+            entries: recording.entries,
+            next:    0,
+        }
+    }
+
+    /// Applies, in order, every entry due at or before the world's current
+    /// generation. Call before each `World::update`.
+    pub fn apply<S: CellState>(
+        &mut self,
+        world: &mut World<S>,
+    ) {
+        while let Some(entry) = self.entries.get(self.next) {
+            if entry.generation > world.generation {
+                break;
+            }
+            apply(world, entry);
+            self.next += 1;
+        }
+    }
+
+    pub fn done(&self) -> bool { self.next >= self.entries.len() }
+}
+
+/// Whether an action changes the world and so belongs in a recording.
+pub fn edits(action: &Action) -> bool {
+    match action {
+        Clear | Step | Rule(_) | Select(_) | NextField | PrevField | Put |
+        Remove | Pick | PlaceAnt | PlaceTurmite(_) | ClearAnts => true,
+        _ => false,
+    }
+}
+
+pub fn apply<S: CellState>(
+    world: &mut World<S>,
+    entry: &Entry,
+) {
+    match (&entry.action, &entry.pos) {
+        (Clear, _) => world.end(),
+        (Step, _) => world.update(),
+        (Rule(name), _) => {
+            if let Err(e) = world.set_rule(name) {
+                eprintln!("recording: {}", e);
+            }
+        }
+        (Select(i), _) => world.palette.select(*i),
+        (NextField, _) => world.palette.next(),
+        (PrevField, _) => world.palette.prev(),
+        (Put, Some(pos)) => world.put(pos),
+        (Remove, Some(pos)) => world.remove(pos),
+        (Pick, Some(pos)) => world.pick(pos),
+        (PlaceAnt, Some(pos)) => world.place_ant(pos),
+        (PlaceTurmite(rule), Some(pos)) => match rule.parse::<Turmite>() {
+            Ok(rule) => world.ants.push(Ant::new(*pos, rule)),
+            Err(e) => eprintln!("recording: {}", e),
+        },
+        (ClearAnts, _) => world.ants.clear(),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::locate;
+
+    #[test]
+    fn replays_from_the_starting_world() {
+        let mut world = World::new();
+        world.put(&locate(1, 1));
+        let mut recording = Recording::start(&world);
+        assert_eq!(world.live().len(), 1);
+        world.put(&locate(2, 2));
+        recording.record(&world, Put, Some(locate(2, 2)));
+
+        let mut replayed = World::new();
+        replayed.put(&locate(5, 5));
+        let mut playback = Playback::start(recording, &mut replayed);
+        playback.apply(&mut replayed);
+        assert!(playback.done());
+        assert_eq!(replayed.live(), world.live());
+    }
+}
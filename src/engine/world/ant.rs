@@ -0,0 +1,319 @@
+use super::{locate, Point};
+
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Heading {
+    N,
+    E,
+    S,
+    W,
+}
+/// A turn relative to an ant's heading.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Turn {
+    None,
+    Right,
+    UTurn,
+    Left,
+}
+/// What a turmite does on a tile of some colour in some state.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    /// Colour written to the tile.
+    pub write: usize,
+    pub turn:  Turn,
+    /// State taken next.
+    pub next:  usize,
+}
+/// A turmite rule: a transition per state and tile colour. Written either as
+/// a Langton's ant string of `L`, `R`, `N` and `U` turns, one per colour, or
+/// in Golly's turmite notation, `{{{write, turn, next}, ..}, ..}` with turns
+/// 1 for none, 2 right, 4 back and 8 left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "TurmiteDef")]
+pub struct Turmite {
+    name:  String,
+    table: Vec<Vec<Transition>>,
+}
+/// A mobile agent walking the tile grid. Colour 0 is an empty tile and
+/// colour `n` a tile holding the `n`th palette field.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "AntDef")]
+pub struct Ant {
+    pub pos:     Point<Point<u16>, usize>,
+    pub heading: Heading,
+    /// Turmite state, indexing the rule's table.
+    pub state:   usize,
+    pub rule:    Turmite,
+}
+/// A turmite as saved, checked by `Turmite::new` when loaded.
+#[derive(Deserialize)]
+struct TurmiteDef {
+    name:  String,
+    table: Vec<Vec<Transition>>,
+}
+/// An ant as saved, checked to be on a tile within its chunk and in one of its
+/// turmite's states when loaded.
+#[derive(Deserialize)]
+struct AntDef {
+    pos:     Point<Point<u16>, usize>,
+    heading: Heading,
+    state:   usize,
+    rule:    Turmite,
+}
+
+impl Heading {
+    pub fn turn(
+        self,
+        turn: Turn,
+    ) -> Self {
+        const HEADINGS: [Heading; 4] =
+            [Heading::N, Heading::E, Heading::S, Heading::W];
+        let by = match turn {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        };
+        HEADINGS[(self as usize + by) % 4]
+    }
+
+    /// One tile ahead, in tiles.
+    pub fn offset(self) -> (i64, i64) {
+        match self {
+            Heading::N => (0, -1),
+            Heading::E => (1, 0),
+            Heading::S => (0, 1),
+            Heading::W => (-1, 0),
+        }
+    }
+}
+
+impl Turmite {
+    /// A turmite with a transition for the same number of colours in every
+    /// state, each writing a colour and entering a state that exist.
+    fn new(
+        name: String,
+        table: Vec<Vec<Transition>>,
+    ) -> Result<Self, String> {
+        let colours = table.first().map_or(0, Vec::len);
+        let fits = |t: &Transition| t.write < colours && t.next < table.len();
+        let row = |s: &Vec<Transition>| s.len() == colours && s.iter().all(fits);
+        match colours > 0 && table.iter().all(row) {
+            true => Ok(Self { name, table }),
+            false => Err(format!("invalid turmite {}", name)),
+        }
+    }
+
+    /// Langton's ant.
+    pub fn ant() -> Self { "RL".parse().unwrap() }
+
+    fn letters(s: &str) -> Result<Self, String> {
+        let turns = s
+            .chars()
+            .map(|c| match c.to_ascii_uppercase() {
+                'N' => Ok(Turn::None),
+                'R' => Ok(Turn::Right),
+                'U' => Ok(Turn::UTurn),
+                'L' => Ok(Turn::Left),
+                c => Err(format!("unexpected {:?} in ant rule {}", c, s)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let n = turns.len();
+        let table = vec![turns
+            .into_iter()
+            .enumerate()
+            .map(|(c, turn)| Transition {
+                write: (c + 1) % n,
+                turn,
+                next: 0,
+            })
+            .collect()];
+        Self::new(s.to_string(), table)
+    }
+
+    fn golly(s: &str) -> Result<Self, String> {
+        let error = || format!("invalid turmite {}", s);
+        let (mut table, mut state, mut numbers) = (vec![], vec![], vec![]);
+        let (mut depth, mut number) = (0, String::new());
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            match c {
+                '0'..='9' => number.push(c),
+                ',' | '}' if !number.is_empty() => {
+                    numbers.push(number.parse::<usize>().map_err(|_| error())?);
+                    number.clear();
+                }
+                _ => {}
+            }
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    match depth {
+                        3 => match numbers.as_slice() {
+                            &[write, turn, next] => state.push(Transition {
+                                write,
+                                turn: match turn {
+                                    1 => Turn::None,
+                                    2 => Turn::Right,
+                                    4 => Turn::UTurn,
+                                    8 => Turn::Left,
+                                    _ => return Err(error()),
+                                },
+                                next,
+                            }),
+                            _ => return Err(error()),
+                        },
+                        2 => table.push(std::mem::take(&mut state)),
+                        1 => {}
+                        _ => return Err(error()),
+                    }
+                    numbers.clear();
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+        match depth {
+            0 => Self::new(s.to_string(), table),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl FromStr for Turmite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("empty ant rule".to_string()),
+            s if s.starts_with('{') => Self::golly(s),
+            s => Self::letters(s),
+        }
+    }
+}
+
+impl TryFrom<TurmiteDef> for Turmite {
+    type Error = String;
+
+    fn try_from(def: TurmiteDef) -> Result<Self, Self::Error> {
+        Self::new(def.name, def.table)
+    }
+}
+
+impl fmt::Display for Turmite {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl TryFrom<AntDef> for Ant {
+    type Error = String;
+
+    fn try_from(def: AntDef) -> Result<Self, Self::Error> {
+        if !def.pos.in_chunk() {
+            return Err(format!("no tile {} in a chunk", def.pos.1));
+        }
+        match def.state < def.rule.table.len() {
+            true => Ok(Self {
+                pos:     def.pos,
+                heading: def.heading,
+                state:   def.state,
+                rule:    def.rule,
+            }),
+            false => {
+                Err(format!("no state {} in turmite {}", def.state, def.rule))
+            }
+        }
+    }
+}
+
+impl Ant {
+    pub fn new(
+        pos: Point<Point<u16>, usize>,
+        rule: Turmite,
+    ) -> Self {
+        Self {
+            pos,
+            heading: Heading::N,
+            state: 0,
+            rule,
+        }
+    }
+
+    /// Turns and changes state for the colour of the tile underneath,
+    /// returning the colour to write to it. Colours the rule doesn't know
+    /// read as the last one it does.
+    pub fn step(
+        &mut self,
+        colour: usize,
+    ) -> usize {
+        let row = &self.rule.table[self.state];
+        let t = row[colour.min(row.len() - 1)];
+        self.heading = self.heading.turn(t.turn);
+        self.state = t.next;
+        t.write
+    }
+
+    /// Moves one tile ahead, wrapping around the edges of the world.
+    pub fn advance(&mut self) {
+        let [x, y] = self.pos.coords();
+        let (dx, dy) = self.heading.offset();
+        self.pos = locate(x + dx, y + dy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        let ant = Turmite::ant();
+        assert_eq!(ant.table.len(), 1);
+        assert_eq!(ant.table[0].len(), 2);
+        let golly = "{{{1, 2, 0}, {0, 8, 0}}}".parse::<Turmite>().unwrap();
+        assert_eq!(golly.table, ant.table);
+        assert!("RX".parse::<Turmite>().is_err());
+        assert!("{{{1, 2, 1}, {0, 8, 0}}}".parse::<Turmite>().is_err());
+        assert!("{{{1, 2, 0}}, {{0, 8, 0}, {1, 2, 0}}}"
+            .parse::<Turmite>()
+            .is_err());
+    }
+
+    #[test]
+    fn saved_ants_round_trip() {
+        let ant = Ant::new(locate(5, 7), Turmite::ant());
+        let json = serde_json::to_string(&ant).unwrap();
+        let loaded = serde_json::from_str::<Ant>(&json).unwrap();
+        assert_eq!((loaded.pos, loaded.state), (ant.pos, ant.state));
+        assert_eq!(loaded.rule.table, ant.rule.table);
+    }
+
+    #[test]
+    fn rejects_invalid_saved_ants() {
+        let ant = Ant::new(locate(0, 0), Turmite::ant());
+        let mut json = serde_json::to_value(&ant).unwrap();
+        json["state"] = 1.into();
+        assert!(serde_json::from_value::<Ant>(json.clone()).is_err());
+
+        json["state"] = 0.into();
+        json["pos"][1] = 1024.into();
+        assert!(serde_json::from_value::<Ant>(json.clone()).is_err());
+        json["pos"][1] = 1023.into();
+        assert!(serde_json::from_value::<Ant>(json.clone()).is_ok());
+
+        json["rule"]["table"] = serde_json::json!([[]]);
+        assert!(serde_json::from_value::<Ant>(json.clone()).is_err());
+        json["rule"]["table"] = serde_json::json!([]);
+        assert!(serde_json::from_value::<Ant>(json.clone()).is_err());
+        json["rule"]["table"] = serde_json::json!([[
+            {"write": 2, "turn": "Left", "next": 0}
+        ]]);
+        assert!(serde_json::from_value::<Ant>(json).is_err());
+    }
+}
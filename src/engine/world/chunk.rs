@@ -1,4 +1,4 @@
-use super::{tile::Tile, Point};
+use super::{render::Painter, state::CellState, tile::Tile, Point};
 
 use array_macro::array;
 use hilbert::Point as HPoint;
@@ -10,37 +10,94 @@ use std::{
     hash::{Hash, Hasher},
 };
 
-#[derive(Eq, Clone, Debug)]
-pub struct Chunk {
+#[derive(Clone, Debug)]
+pub struct Chunk<S = Tile> {
     pub pos:     Point<u16>,
-    pub tiles:   [Tile; 1024],
-    pub border:  [Option<Tile>; 128],
+    pub tiles:   [S; 1024],
+    pub age:     [u32; 1024],
+    /// Generation of the last change to each tile.
+    pub touched: [u64; 1024],
+    pub border:  [Option<S>; 128],
     pub changed: bool,
 }
 
-impl Default for Chunk {
+impl<S: CellState> Default for Chunk<S> {
     fn default() -> Self {
-        let tiles = array![x=>Tile::new(&Point::from(x)); 1024];
+        let tiles = array![S::default(); 1024];
+        let age = [0; 1024];
+        let touched = [0; 1024];
         let border = array![None; 128];
         let changed = false;
         Self {
             pos: Point::default(),
             tiles,
+            age,
+            touched,
             border,
             changed,
         }
     }
 }
-impl Chunk {
+impl<S: CellState> Chunk<S> {
+    /// Whether the chunk lies within the range returned by `Camera::visible`.
     pub fn on_screen(
         &self,
-        camera: (f64, f64, f64, f64),
+        (min, max): (Point<u16>, Point<u16>),
     ) -> bool {
-        self.pos.0 >= (camera.0 - camera.2) as u16 &&
-            self.pos.1 >= (camera.1 - camera.3) as u16 &&
-            self.pos.0 <= (camera.0 + camera.2) as u16 &&
-            self.pos.1 <= (camera.1 + camera.3) as u16
-        // true
+        self.pos.0 >= min.0 &&
+            self.pos.1 >= min.1 &&
+            self.pos.0 <= max.0 &&
+            self.pos.1 <= max.1
+    }
+
+    /// One RGBA pixel per tile, transparent where empty.
+    pub fn rgba(
+        &self,
+        painter: &Painter,
+    ) -> Vec<u8> {
+        let mut pixels = vec![0u8; 32 * 32 * 4];
+        pixels
+            .chunks_mut(4)
+            .enumerate()
+            .filter(|&(i, _)| self.tiles[i].alive())
+            .for_each(|(i, px)| {
+                px.iter_mut()
+                    .zip(painter.color(self, i).iter())
+                    .for_each(|(p, c)| *p = (c.max(0.).min(1.) * 255.) as u8);
+            });
+        pixels
+    }
+
+    /// Average colour of each `k`×`k` block, fainter the fewer tiles live.
+    pub fn blocks(
+        &self,
+        k: usize,
+        painter: &Painter,
+    ) -> Vec<(Point<u8>, [f32; 4])> {
+        let k = k.max(1).min(32);
+        let mut blocks = Vec::new();
+        for by in (0..32).step_by(k) {
+            for bx in (0..32).step_by(k) {
+                let mut c = [0f32; 4];
+                let mut n = 0;
+                for y in by..by + k {
+                    for x in bx..bx + k {
+                        let i = y * 32 + x;
+                        if self.tiles[i].alive() {
+                            let tc = painter.color(self, i);
+                            c.iter_mut().zip(tc.iter()).for_each(|(a, b)| *a += b);
+                            n += 1;
+                        }
+                    }
+                }
+                if n > 0 {
+                    c.iter_mut().for_each(|a| *a /= n as f32);
+                    c[3] *= 0.25 + 0.75 * n as f32 / (k * k) as f32;
+                    blocks.push((Point(bx as u8, by as u8), c));
+                }
+            }
+        }
+        blocks
     }
 
     pub fn hilbert_index(
@@ -55,7 +112,7 @@ impl Chunk {
     }
 }
 
-impl Ord for Chunk {
+impl<S: CellState> Ord for Chunk<S> {
     fn cmp(
         &self,
         other: &Self,
@@ -63,7 +120,7 @@ impl Ord for Chunk {
         self.hilbert_index(&0).cmp(&&other.hilbert_index(&1))
     }
 }
-impl PartialOrd for Chunk {
+impl<S: CellState> PartialOrd for Chunk<S> {
     fn partial_cmp(
         &self,
         other: &Self,
@@ -71,7 +128,7 @@ impl PartialOrd for Chunk {
         Some(self.cmp(other))
     }
 }
-impl PartialEq for Chunk {
+impl<S> PartialEq for Chunk<S> {
     fn eq(
         &self,
         other: &Self,
@@ -79,7 +136,8 @@ impl PartialEq for Chunk {
         self.pos == other.pos
     }
 }
-impl Hash for Chunk {
+impl<S> Eq for Chunk<S> {}
+impl<S> Hash for Chunk<S> {
     fn hash<H: Hasher>(
         &self,
         state: &mut H,
@@ -88,7 +146,7 @@ impl Hash for Chunk {
     }
 }
 
-impl Equivalent<Point<u16>> for Chunk {
+impl<S> Equivalent<Point<u16>> for Chunk<S> {
     fn equivalent(
         &self,
         key: &Point<u16>,
@@ -96,10 +154,10 @@ impl Equivalent<Point<u16>> for Chunk {
         &self.pos == key
     }
 }
-impl Equivalent<Chunk> for Point<u16> {
+impl<S> Equivalent<Chunk<S>> for Point<u16> {
     fn equivalent(
         &self,
-        key: &Chunk,
+        key: &Chunk<S>,
     ) -> bool {
         self == &key.pos
     }
@@ -0,0 +1,183 @@
+use super::{
+    field::Field,
+    logic::{CellRule, Neighbourhood},
+    palette::Palette,
+    render::ramp,
+    state::CellState,
+};
+use crate::functions::{from_json, write_file};
+
+use serde::{Deserialize, Serialize};
+
+/// States below this are rounded down to empty, so that growth doesn't leave
+/// a haze of nearly empty tiles to step forever.
+const EPSILON: f32 = 1e-3;
+
+/// A continuous cell state in `0..=1`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Continuous(pub f32);
+
+/// Kernel and growth parameters of a Lenia rule. The defaults grow Orbium.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LeniaConfig {
+    /// Kernel radius, in tiles.
+    pub radius: u32,
+    /// Heights of the kernel's concentric rings, innermost first.
+    pub peaks:  Vec<f32>,
+    /// Neighbourhood sum at which growth peaks.
+    pub mu:     f32,
+    /// Width of the growth peak.
+    pub sigma:  f32,
+    /// Fraction of the growth applied per generation.
+    pub dt:     f32,
+}
+/// Lenia: a smooth kernel convolved over the neighbourhood, passed through a
+/// gaussian growth function and integrated over `dt`.
+#[derive(Clone, Debug)]
+pub struct Lenia {
+    config:  LeniaConfig,
+    /// Normalised kernel weight of each neighbour, in `offsets` order.
+    weights: Vec<f32>,
+}
+
+impl Default for LeniaConfig {
+    fn default() -> Self {
+        Self {
+            radius: 13,
+            peaks:  vec![1.],
+            mu:     0.15,
+            sigma:  0.015,
+            dt:     0.1,
+        }
+    }
+}
+impl LeniaConfig {
+    pub fn save_lenia(&self) {
+        serde_json::to_writer_pretty(
+            &write_file("assets/config/lenia.json".to_string()),
+            &self,
+        )
+        .expect("Couldn't write json to lenia.");
+    }
+
+    pub fn load_lenia(&mut self) {
+        let path = "assets/config/lenia.json".to_string();
+        if let Ok(c) = serde_json::from_str(&from_json(path)) {
+            *self = c;
+        };
+    }
+}
+
+impl Lenia {
+    pub fn new(config: LeniaConfig) -> Self {
+        let radius = config.radius.max(1) as f32;
+        let mut weights = Neighbourhood::Disc(config.radius)
+            .offsets()
+            .iter()
+            .map(|&(dx, dy)| {
+                let r = ((dx * dx + dy * dy) as f32).sqrt() / radius;
+                shell(&config.peaks, r)
+            })
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f32>();
+        if total > 0. {
+            weights.iter_mut().for_each(|w| *w /= total);
+        }
+        Self { config, weights }
+    }
+
+    /// The rule with parameters from assets/config/lenia.json.
+    pub fn load() -> Self {
+        let mut config = LeniaConfig::default();
+        config.load_lenia();
+        Self::new(config)
+    }
+
+    /// Maps a neighbourhood sum to a growth rate in `-1..=1`.
+    pub fn growth(
+        &self,
+        u: f32,
+    ) -> f32 {
+        let LeniaConfig { mu, sigma, .. } = self.config;
+        2. * (-(u - mu).powi(2) / (2. * sigma * sigma)).exp() - 1.
+    }
+}
+
+/// Kernel height at distance `r`, as a fraction of the radius. Each ring is
+/// a smooth bump scaled by its peak.
+fn shell(
+    peaks: &[f32],
+    r: f32,
+) -> f32 {
+    if peaks.is_empty() || r <= 0. || r >= 1. {
+        return 0.;
+    }
+    let br = r * peaks.len() as f32;
+    let ring = (br.floor() as usize).min(peaks.len() - 1);
+    let x = br - ring as f32;
+    let bump = if x > 0. && x < 1. {
+        (4. - 1. / (x * (1. - x))).exp()
+    } else {
+        0.
+    };
+    peaks[ring] * bump
+}
+
+impl CellState for Continuous {
+    fn rule(name: &str) -> Result<Box<dyn CellRule<State = Self>>, String> {
+        match name {
+            "default" | "lenia" => Ok(Box::new(Lenia::load())),
+            _ => Err(format!("unknown continuous rule: {}", name)),
+        }
+    }
+
+    fn alive(&self) -> bool { self.0 > 0. }
+
+    fn same(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self == other
+    }
+
+    fn color(
+        &self,
+        _palette: &Palette,
+    ) -> [f32; 4] {
+        ramp(self.0)
+    }
+
+    /// Painting fills the cell.
+    fn put(
+        &mut self,
+        _field: Field,
+    ) {
+        self.0 = 1.;
+    }
+
+    fn inspect(&self) -> Vec<String> { vec![format!("state: {:.3}", self.0)] }
+}
+
+impl CellRule for Lenia {
+    type State = Continuous;
+
+    fn name(&self) -> String { "lenia".to_string() }
+
+    fn neighbourhood(&self) -> Neighbourhood {
+        Neighbourhood::Disc(self.config.radius)
+    }
+
+    fn step(
+        &self,
+        cell: &Continuous,
+        neighbours: &[&Continuous],
+    ) -> Continuous {
+        let u = neighbours
+            .iter()
+            .zip(&self.weights)
+            .map(|(n, w)| n.0 * w)
+            .sum::<f32>();
+        let next = (cell.0 + self.config.dt * self.growth(u)).max(0.).min(1.);
+        Continuous(if next < EPSILON { 0. } else { next })
+    }
+}
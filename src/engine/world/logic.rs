@@ -1,41 +1,112 @@
-use self::Rule::*;
-use super::field::Field;
-use crate::functions::{from_json, read_file};
+use super::{field::Field, script::ScriptRule, tile::Tile};
+use crate::functions::{from_json, write_file};
 
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use std::collections::HashMap;
+use std::{collections::HashMap, convert::TryFrom, fmt, str::FromStr};
 
+/// Cells whose states are compared when stepping a cell.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Neighbourhood {
+    /// The eight surrounding cells.
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+    /// Every cell within a euclidean radius, in tiles.
+    Disc(u32),
+}
+/// A cellular automaton rule. Implement it to add rules without touching
+/// `World`.
+pub trait CellRule {
+    type State;
+
+    fn name(&self) -> String;
+
+    fn neighbourhood(&self) -> Neighbourhood { Neighbourhood::Moore }
+
+    /// Whether empty cells next to live ones can change. Rules that only
+    /// change live cells return false so empty ones aren't visited.
+    fn spawns(&self) -> bool { true }
+
+    /// Next state of `cell`, given its neighbours in the order of
+    /// `Neighbourhood::offsets`.
+    fn step(
+        &self,
+        cell: &Self::State,
+        neighbours: &[&Self::State],
+    ) -> Self::State;
+
+    /// Whether stepping keeps every field's world total unchanged. Debug
+    /// builds check it after each generation.
+    fn conserves(&self) -> bool { false }
+
+    /// Whether each generation steps the rule until no cell is `unstable`,
+    /// resolving a whole avalanche before the next edit.
+    fn settles(&self) -> bool { false }
+
+    /// Whether a cell changes when stepped, for rules that settle.
+    fn unstable(
+        &self,
+        _cell: &Self::State,
+    ) -> bool {
+        false
+    }
+
+    /// Called once before each generation.
+    fn prepare(&mut self) {}
+
+    /// Takes the last error the rule ran into, if any.
+    fn error(&mut self) -> Option<String> { None }
+}
+pub type TileRule = Box<dyn CellRule<State = Tile>>;
+
+/// Grains at which a sandpile tile topples.
+pub const TOPPLE: u32 = 4;
+
+/// Names of the built-in field rules, as stored in rulesets.
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Rule {
     Spread,
     Collect,
     Multiply,
+    Diffuse,
+    Sandpile,
 }
+/// Empty cells take one unit of every field around them.
+#[derive(Copy, Clone, Debug)]
+pub struct Spread;
+/// Live cells next to other live cells collect more of the first field. This
+/// is the default rule.
+#[derive(Copy, Clone, Debug)]
+pub struct Collect;
+/// Each field of a live cell grows by the number of neighbours holding it.
+#[derive(Copy, Clone, Debug)]
+pub struct Multiply;
+/// Each field is shared out evenly between a tile and its neighbours, the
+/// tile keeping the remainder.
+#[derive(Copy, Clone, Debug)]
+pub struct Diffuse;
+/// Abelian sandpile: a tile holding `TOPPLE` or more grains of a field passes
+/// one to each orthogonal neighbour.
+#[derive(Copy, Clone, Debug)]
+pub struct Sandpile;
 #[serde_as]
 #[derive(Serialize, Deserialize)]
 pub struct LogicHandler {
     ruleset: HashMap<Field, Rule>,
 }
 impl LogicHandler {
-    pub fn evaluate(
-        &mut self,
+    /// Rule configured for a field, if any.
+    pub fn rule(
+        &self,
         field: &Field,
-        other: &Field,
-    ) {
-        if self.ruleset.contains_key(field) && self.ruleset.contains_key(other) {
-            match (self.ruleset.get(field), self.ruleset.get(other)) {
-                (Some(&Spread), _) => {}
-                (Some(&Collect), _) => {}
-                (Some(&Multiply), _) => {}
-                (None, _) => {}
-            }
-        }
+    ) -> Option<TileRule> {
+        self.ruleset.get(field).map(|&rule| rule.boxed())
     }
 
     pub fn save_ruleset(&self) {
         serde_json::to_writer(
-            &read_file("assets/config/ruleset.json".to_string()),
+            &write_file("assets/config/ruleset.json".to_string()),
             &self.ruleset,
         )
         .expect("Couldn't write json to keymap.");
@@ -48,3 +119,287 @@ impl LogicHandler {
         };
     }
 }
+
+/// Outer totalistic rule over the Moore neighbourhood, written `B3/S23`.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Life {
+    /// Live neighbour counts at which an empty tile is born.
+    pub birth:   [bool; 9],
+    /// Live neighbour counts at which a live tile survives.
+    pub survive: [bool; 9],
+}
+
+impl Neighbourhood {
+    /// Offsets of the neighbours, row by row from the top left.
+    pub fn offsets(self) -> Vec<(i64, i64)> {
+        let r = match self {
+            Neighbourhood::Disc(r) => r as i64,
+            _ => 1,
+        };
+        let mut offsets = Vec::new();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let inside = match self {
+                    Neighbourhood::Moore => true,
+                    Neighbourhood::VonNeumann => dx.abs() + dy.abs() == 1,
+                    Neighbourhood::Disc(_) => dx * dx + dy * dy <= r * r,
+                };
+                if inside && (dx, dy) != (0, 0) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl Rule {
+    pub fn boxed(self) -> TileRule {
+        match self {
+            Rule::Spread => Box::new(Spread),
+            Rule::Collect => Box::new(Collect),
+            Rule::Multiply => Box::new(Multiply),
+            Rule::Diffuse => Box::new(Diffuse),
+            Rule::Sandpile => Box::new(Sandpile),
+        }
+    }
+}
+
+/// A tile with `cell`'s rule holding the nonzero quantities in `totals`.
+fn from_totals(
+    cell: &Tile,
+    totals: HashMap<u32, u64>,
+) -> Tile {
+    let mut next = Tile::default();
+    next.rule = cell.rule;
+    totals
+        .into_iter()
+        .filter(|&(_, q)| q > 0)
+        .for_each(|(id, q)| {
+            next += Field(id, u32::try_from(q).expect("Field overflow."));
+        });
+    next
+}
+
+/// Parses a rule name as used by the console and saved worlds: `default`, a
+/// built-in rule name, a life-like rule such as `B3/S23`, or `script:<path>`.
+pub fn parse(name: &str) -> Result<TileRule, String> {
+    match name {
+        "default" | "Collect" => Ok(Box::new(Collect)),
+        "Spread" => Ok(Box::new(Spread)),
+        "Multiply" => Ok(Box::new(Multiply)),
+        "Diffuse" => Ok(Box::new(Diffuse)),
+        "Sandpile" => Ok(Box::new(Sandpile)),
+        _ if name.starts_with("script:") => {
+            let mut script = ScriptRule::new(&name["script:".len()..]);
+            match script.active() {
+                true => Ok(Box::new(script)),
+                false => Err(script
+                    .error()
+                    .unwrap_or_else(|| format!("{}: no such script", name))),
+            }
+        }
+        _ => Ok(Box::new(name.parse::<Life>()?)),
+    }
+}
+
+impl CellRule for Spread {
+    type State = Tile;
+
+    fn name(&self) -> String { "Spread".to_string() }
+
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let mut next = cell.clone();
+        if cell.members == 0 {
+            neighbours
+                .iter()
+                .flat_map(|n| n.fields())
+                .for_each(|f| next += Field(f.0, 1));
+        }
+        next
+    }
+}
+impl CellRule for Collect {
+    type State = Tile;
+
+    fn name(&self) -> String { "Collect".to_string() }
+
+    fn spawns(&self) -> bool { false }
+
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let mut next = cell.clone();
+        if cell.members > 0 && neighbours.iter().any(|n| n.members > 0) {
+            next += Field(1, 10);
+        }
+        next
+    }
+}
+impl CellRule for Multiply {
+    type State = Tile;
+
+    fn name(&self) -> String { "Multiply".to_string() }
+
+    fn spawns(&self) -> bool { false }
+
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let mut next = cell.clone();
+        cell.fields().for_each(|&f| {
+            let n = neighbours.iter().filter(|n| n.has(f.0)).count() as u32;
+            next += Field(f.0, f.1.saturating_mul(n));
+        });
+        next
+    }
+}
+impl CellRule for Diffuse {
+    type State = Tile;
+
+    fn name(&self) -> String { "Diffuse".to_string() }
+
+    fn conserves(&self) -> bool { true }
+
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let n = neighbours.len() as u32;
+        let share = |q: u32| q / (n + 1);
+        // Up to this much, a tile can keep its remainder and take a full share
+        // from every neighbour without overflowing.
+        let full = u32::MAX - share(u32::MAX) * n;
+        let takes = |t: &Tile, id: u32| t.quantity(id) <= full;
+        let mut totals = HashMap::new();
+        cell.fields().for_each(|f| {
+            let given = neighbours.iter().filter(|&&t| takes(t, f.0)).count();
+            let kept = f.1 - share(f.1) * given as u32;
+            *totals.entry(f.0).or_insert(0u64) += kept as u64;
+        });
+        neighbours
+            .iter()
+            .flat_map(|t| t.fields())
+            .filter(|f| takes(cell, f.0))
+            .for_each(|f| *totals.entry(f.0).or_insert(0) += share(f.1) as u64);
+        from_totals(cell, totals)
+    }
+}
+impl CellRule for Sandpile {
+    type State = Tile;
+
+    fn name(&self) -> String { "Sandpile".to_string() }
+
+    fn neighbourhood(&self) -> Neighbourhood { Neighbourhood::VonNeumann }
+
+    fn conserves(&self) -> bool { true }
+
+    fn settles(&self) -> bool { true }
+
+    fn unstable(
+        &self,
+        cell: &Tile,
+    ) -> bool {
+        cell.fields().any(|f| f.1 >= TOPPLE)
+    }
+
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let mut totals = HashMap::new();
+        cell.fields().for_each(|f| {
+            let kept = if f.1 >= TOPPLE { f.1 - TOPPLE } else { f.1 };
+            *totals.entry(f.0).or_insert(0u64) += kept as u64;
+        });
+        neighbours
+            .iter()
+            .flat_map(|t| t.fields())
+            .filter(|f| f.1 >= TOPPLE)
+            .for_each(|f| *totals.entry(f.0).or_insert(0) += 1);
+        from_totals(cell, totals)
+    }
+}
+impl CellRule for Life {
+    type State = Tile;
+
+    fn name(&self) -> String { self.to_string() }
+
+    /// Births take the most common field among the live neighbours.
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        let live = neighbours.iter().filter(|n| n.members > 0);
+        let count = live.clone().count();
+        match cell.members > 0 {
+            true if self.survive[count] => cell.clone(),
+            false if self.birth[count] => {
+                let mut counts = HashMap::new();
+                live.flat_map(|n| n.fields()).for_each(|f| {
+                    *counts.entry(f.0).or_insert(0) += 1;
+                });
+                let id = counts.into_iter().max_by_key(|&(id, n)| (n, id));
+                let mut next = Tile::default();
+                if let Some((id, _)) = id {
+                    next += Field(id, 10);
+                }
+                next
+            }
+            _ => Tile::default(),
+        }
+    }
+}
+
+impl FromStr for Life {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let counts = |part: &str, prefix: char| {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or_else(|| format!("expected {}<counts> in {}", prefix, s))?;
+            let mut counts = [false; 9];
+            for d in digits.chars() {
+                match d.to_digit(10) {
+                    Some(n) if n < 9 => counts[n as usize] = true,
+                    _ => return Err(format!("invalid count {} in {}", d, s)),
+                }
+            }
+            Ok(counts)
+        };
+        match s.split('/').collect::<Vec<_>>().as_slice() {
+            [birth, survive] => Ok(Self {
+                birth:   counts(birth, 'B')?,
+                survive: counts(survive, 'S')?,
+            }),
+            _ => Err(format!("expected B<counts>/S<counts>, got {}", s)),
+        }
+    }
+}
+impl fmt::Display for Life {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let digits = |counts: &[bool; 9]| {
+            (0..9)
+                .filter(|&n| counts[n])
+                .map(|n| n.to_string())
+                .collect::<String>()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survive))
+    }
+}
@@ -1,19 +1,42 @@
+pub mod ant;
 pub mod chunk;
 pub mod field;
+pub mod lenia;
 pub mod logic;
+pub mod multistate;
+pub mod palette;
+pub mod pattern;
+pub mod reaction;
+pub mod render;
+pub mod script;
+pub mod state;
+pub mod stats;
+pub mod table;
 pub mod tile;
 
-use self::{chunk::Chunk, field::Field, tile::Tile};
+use self::{
+    ant::{Ant, Turmite},
+    chunk::Chunk,
+    field::Field,
+    logic::{CellRule, Collect, TileRule},
+    palette::Palette,
+    script::ScriptRule,
+    state::CellState,
+    stats::Stats,
+    tile::Tile,
+};
+use crate::functions::from_json;
 
 use hilbert::Point as HPoint;
 use indexmap::IndexMap;
-use logic::Rule::*;
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     convert::TryFrom,
+    fs::File,
+    path::Path,
     ops::{Add, Mul},
 };
 
@@ -22,41 +45,168 @@ use std::{
 )]
 pub struct Point<T, D = T>(pub T, pub D);
 
-pub struct World {
-    pub chunks: IndexMap<Point<u16>, Chunk>,
-    changed:    bool,
+const HISTORY: usize = 512;
+/// Tiles changed during an update, each with whether it was alive before.
+type Changes = Vec<(Point<Point<u16>, usize>, bool)>;
+/// Number of tiles along one side of the world.
+const SIDE: i64 = (u16::MAX as i64 + 1) * 32;
+
+pub struct World<S = Tile> {
+    pub chunks:     IndexMap<Point<u16>, Chunk<S>>,
+    pub palette:    Palette,
+    pub generation: u64,
+    pub rule:       Box<dyn CellRule<State = S>>,
+    pub ants:       Vec<Ant>,
+    history:        VecDeque<Stats>,
+    changed:        bool,
 }
 
 impl World {
+    /// A field-based world, run by the startup script if there is one.
     pub fn new() -> Self {
+        let rule: TileRule = match Path::new(script::PATH).exists() {
+            true => Box::new(ScriptRule::new(script::PATH)),
+            false => Box::new(Collect),
+        };
+        Self::with_rule(rule)
+    }
+
+    /// Moves up to `field.1` of a field between tiles, as much as the
+    /// receiving tile can hold, and returns the quantity moved.
+    pub fn transfer(
+        &mut self,
+        from: &Point<Point<u16>, usize>,
+        to: &Point<Point<u16>, usize>,
+        field: Field,
+    ) -> u32 {
+        let quantity = |t: Option<&Tile>| t.map_or(0, |t| t.quantity(field.0));
+        let room = u32::MAX - quantity(self.tile(to));
+        let moved = field.1.min(quantity(self.tile(from))).min(room);
+        if from == to || moved == 0 {
+            return 0;
+        }
+        let mut source = self.tile(from).cloned().unwrap_or_default();
+        source.take(Field(field.0, moved));
+        self.set(from, source);
+        let mut target = self.tile(to).cloned().unwrap_or_default();
+        target += Field(field.0, moved);
+        self.set(to, target);
+        moved
+    }
+}
+
+impl<S: CellState> World<S> {
+    pub fn with_rule(rule: Box<dyn CellRule<State = S>>) -> Self {
         let chunks = IndexMap::new();
+        let mut palette = Palette::default();
+        let generation = 0;
+        let ants = Vec::new();
+        let history = VecDeque::with_capacity(HISTORY);
         let changed = true;
-        Self { chunks, changed }
+        palette.load_palette();
+        Self {
+            chunks,
+            palette,
+            generation,
+            rule,
+            ants,
+            history,
+            changed,
+        }
     }
 
-    pub fn interract(
+    /// Replaces the rule by name, along with the palette if the rule comes
+    /// with one.
+    pub fn set_rule(
         &mut self,
-        pos: Point<Point<u16>, Tile>,
-    ) {
-        match self.chunks.get_mut(&pos.0) {
-            Some(chunk) => {
-                let Tile {
-                    pos: _,
-                    members: _,
-                    rule,
-                    ..
-                } = chunk.tiles[pos.1.pos()];
-                // *t.into_mut() *= tile;
-                match rule.unwrap_or(Collect) {
-                    Collect => {}
-                    Spread => {}
-                    Multiply => {}
-                }
-            }
-            None => {
-                // self.chunks.insert(tile);
-            }
+        name: &str,
+    ) -> Result<(), String> {
+        self.rule = S::rule(name)?;
+        if let Some(palette) = S::palette(name) {
+            self.palette = palette;
         }
+        Ok(())
+    }
+
+    pub fn live(&self) -> HashSet<Point<Point<u16>, usize>> {
+        self.chunks
+            .iter()
+            .flat_map(|(&p, chunk)| {
+                chunk
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| t.alive())
+                    .map(move |(i, _)| Point(p, i))
+            })
+            .collect()
+    }
+
+    pub fn census(&self) -> Stats {
+        let mut stats = Stats::default();
+        stats.generation = self.generation;
+        stats.chunks = self.chunks.len();
+        self.chunks.values().for_each(|chunk| {
+            chunk.tiles.iter().filter(|t| t.alive()).for_each(|t| {
+                stats.tiles += 1;
+                t.quantities().iter().for_each(|f| {
+                    *stats.fields.entry(f.0).or_insert(0) += f.1 as u64;
+                });
+            })
+        });
+        stats
+    }
+
+    pub fn history(&self) -> &VecDeque<Stats> { &self.history }
+
+    pub fn total(
+        &self,
+        id: u32,
+    ) -> u64 {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.tiles.iter())
+            .flat_map(|t| t.quantities())
+            .filter(|f| f.0 == id)
+            .map(|f| f.1 as u64)
+            .sum()
+    }
+
+    /// World positions, in tiles, of the centres of all live tiles.
+    pub fn positions(&self) -> impl Iterator<Item = [f64; 2]> + '_ {
+        self.chunks.iter().flat_map(|(&p, chunk)| {
+            chunk
+                .tiles
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.alive())
+                .map(move |(i, _)| {
+                    let pos = p * 32. + Point::<u8>::from(i);
+                    [pos.0 + 0.5, pos.1 + 0.5]
+                })
+        })
+    }
+
+    pub fn centroid(&self) -> Option<[f64; 2]> {
+        let (n, sum) = self.positions().fold((0, [0.; 2]), |(n, s), p| {
+            (n + 1, [s[0] + p[0], s[1] + p[1]])
+        });
+        if n > 0 {
+            Some([sum[0] / n as f64, sum[1] / n as f64])
+        } else {
+            None
+        }
+    }
+
+    /// Corners, in tiles, of the box holding every live tile.
+    pub fn bounds(&self) -> Option<([f64; 2], [f64; 2])> {
+        self.positions().fold(None, |b, p| match b {
+            None => Some((p, p)),
+            Some((min, max)) => Some((
+                [min[0].min(p[0]), min[1].min(p[1])],
+                [max[0].max(p[0]), max[1].max(p[1])],
+            )),
+        })
     }
 
     pub fn remove(
@@ -64,164 +214,401 @@ impl World {
         pos: &Point<Point<u16>, usize>,
     ) {
         if let Some(chunk) = self.chunks.get_mut(&pos.0) {
-            chunk.tiles[pos.1] = Tile::new(&pos.1.into());
+            chunk.tiles[pos.1] = S::default();
+            chunk.age[pos.1] = 0;
+            chunk.touched[pos.1] = self.generation;
+            chunk.changed = true;
             self.changed = true;
         }
     }
 
+    pub fn tile(
+        &self,
+        pos: &Point<Point<u16>, usize>,
+    ) -> Option<&S> {
+        self.chunks.get(&pos.0).map(|chunk| &chunk.tiles[pos.1])
+    }
+
+    /// Selects the field with the largest quantity on the tile at `pos`.
+    pub fn pick(
+        &mut self,
+        pos: &Point<Point<u16>, usize>,
+    ) {
+        let id = self
+            .tile(pos)
+            .and_then(|tile| tile.quantities().into_iter().max_by_key(|f| f.1))
+            .map(|f| f.0);
+        if let Some(id) = id {
+            self.palette.select_id(id);
+        }
+    }
+
     pub fn put(
         &mut self,
         coords: &Point<Point<u16>, usize>,
     ) {
+        let field = self.palette.field();
         if let Some(chunk) = self.chunks.get_mut(&coords.0) {
-            chunk.tiles[coords.1] += Field(1, 10);
-            dbg!(&chunk.tiles[coords.1]);
+            chunk.tiles[coords.1].put(field);
+            chunk.touched[coords.1] = self.generation;
+            chunk.changed = true;
         } else {
-            let mut chunk = Chunk::default();
+            let mut chunk = Chunk::<S>::default();
             chunk.pos = coords.0;
-            chunk.tiles[coords.1] += Field(1, 10);
+            chunk.tiles[coords.1].put(field);
+            chunk.touched[coords.1] = self.generation;
+            chunk.changed = true;
             self.chunks.insert(coords.0, chunk);
             self.changed = true;
         }
     }
 
     pub fn update(&mut self) {
-        let mut neighbours = HashSet::new();
-        self.chunks.iter().for_each(|(&p, chunk)| {
-            // .filter(|&(_, t)| t.changed)
-            chunk.tiles.iter().enumerate().for_each(|(i, t)| {
-                match (i / 32, i % 32, t.members != 0) {
-                    // top left
-                    (0, 0, true) => {
-                        neighbours.insert(Point(p.nx(-1, -1), 1023));
-                        neighbours.insert(Point(p.nx(0, -1), 992));
-                        neighbours.insert(Point(p.nx(0, -1), 993));
-                        neighbours.insert(Point(p.nx(-1, 0), 63));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p.nx(-1, 0), 31));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p, i + 33));
-                    }
-                    // top right
-                    (00, 31, true) => {
-                        neighbours.insert(Point(p.nx(0, -1), 1022));
-                        neighbours.insert(Point(p.nx(0, -1), 1023));
-                        neighbours.insert(Point(p.nx(1, -1), 992));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p.nx(1, 0), 0));
-                        neighbours.insert(Point(p, i + 31));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p.nx(1, 0), 32));
-                    }
-                    // bottom left
-                    (31, 00, true) => {
-                        neighbours.insert(Point(p.nx(-1, 0), 991));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p, i - 31));
-                        neighbours.insert(Point(p.nx(-1, 0), 1023));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p.nx(-1, 1), 1));
-                        neighbours.insert(Point(p.nx(0, 1), 0));
-                        neighbours.insert(Point(p.nx(0, 1), 31));
-                    }
-                    // bottom right
-                    (31, 31, true) => {
-                        neighbours.insert(Point(p, i - 33));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p.nx(1, 0), 960));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p.nx(1, 0), 992));
-                        neighbours.insert(Point(p.nx(0, 1), 1022));
-                        neighbours.insert(Point(p.nx(0, 1), 1023));
-                        neighbours.insert(Point(p.nx(1, 1), 0));
-                    }
-                    // top
-                    (00, _, true) => {
-                        neighbours.insert(Point(p.nx(0, -1), 991 + i));
-                        neighbours.insert(Point(p.nx(0, -1), 992 + i));
-                        neighbours.insert(Point(p.nx(0, -1), 993 + i));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p, i + 31));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p, i + 33));
-                    }
-                    // left
-                    (_, 00, true) => {
-                        neighbours.insert(Point(p.nx(-1, 0), i - 1));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p, i - 31));
-                        neighbours.insert(Point(p.nx(-1, 0), i + 31));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p.nx(-1, 0), i + 63));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p, i + 33));
-                    }
-                    // right
-                    (_, 31, true) => {
-                        neighbours.insert(Point(p, i - 33));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p.nx(1, 0), i - 63));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p.nx(1, 0), i - 31));
-                        neighbours.insert(Point(p, i + 31));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p.nx(1, 0), i + 1));
-                    }
-                    // bottom
-                    (31, _, true) => {
-                        neighbours.insert(Point(p, i - 33));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p, i - 31));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p.nx(0, 1), i - 993));
-                        neighbours.insert(Point(p.nx(0, 1), i - 992));
-                        neighbours.insert(Point(p.nx(0, 1), i - 991));
-                    }
-                    // inside
-                    (_, _, true) => {
-                        neighbours.insert(Point(p, i - 33));
-                        neighbours.insert(Point(p, i - 32));
-                        neighbours.insert(Point(p, i - 31));
-                        neighbours.insert(Point(p, i - 1));
-                        neighbours.insert(Point(p, i + 1));
-                        neighbours.insert(Point(p, i + 31));
-                        neighbours.insert(Point(p, i + 32));
-                        neighbours.insert(Point(p, i + 33));
-                    }
-                    _ => {}
-                }
-            })
+        let before = self.live();
+        // Ants repaint tiles, so totals are only checked without them.
+        let check = self.rule.conserves() && self.ants.is_empty();
+        let totals = match cfg!(debug_assertions) && check {
+            true => Some(self.census().fields),
+            false => None,
+        };
+        self.rule.prepare();
+        let (mut changes, topplings, area) = match self.rule.settles() {
+            true => self.settle(&before),
+            false => (self.step(&before), 0, 0),
+        };
+        changes.extend(self.walk());
+
+        if self.changed {
+            self.hilbert();
+            self.changed = false;
+        }
+
+        self.chunks.values_mut().for_each(|chunk| {
+            let Chunk { tiles, age, .. } = chunk;
+            tiles
+                .iter()
+                .zip(age.iter_mut())
+                .filter(|(t, _)| t.alive())
+                .for_each(|(_, age)| *age += 1);
         });
-        // dbg!("thus");
-        neighbours.iter().for_each(|p| {
-            if let Some(ch) = self.chunks.get_mut(&p.0) {
-                {
-                    match ch.tiles[p.1].members != 0 {
-                        true => {
-                            ch.tiles[p.1] += Field(1, 10);
-                            // dbg!(&ch.tiles[p.1]);
-                        }
-                        false => {}
-                    }
+
+        self.generation += 1;
+        let mut stats = self.census();
+        if let Some(totals) = totals {
+            debug_assert_eq!(
+                totals,
+                stats.fields,
+                "{} changed field totals",
+                self.rule.name()
+            );
+        }
+        let (births, deaths) = self.turnover(changes);
+        stats.births = births;
+        stats.deaths = deaths;
+        stats.avalanche = topplings;
+        stats.area = area;
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+    }
+
+    /// Steps the rule over the `live` tiles, and the empty tiles around them
+    /// if the rule spawns.
+    fn step(
+        &mut self,
+        live: &HashSet<Point<Point<u16>, usize>>,
+    ) -> Changes {
+        let offsets = self.rule.neighbourhood().offsets();
+        let around = |p: &Point<Point<u16>, usize>| {
+            let [x, y] = p.coords();
+            offsets.iter().map(move |(dx, dy)| locate(x + dx, y + dy))
+        };
+        let mut cells = live.clone();
+        if self.rule.spawns() {
+            live.iter().for_each(|p| cells.extend(around(p)));
+        }
+        let empty = S::default();
+        let changes = cells
+            .into_iter()
+            .filter_map(|p| {
+                let cell = self.tile(&p).unwrap_or(&empty);
+                let neighbours = around(&p)
+                    .map(|n| self.tile(&n).unwrap_or(&empty))
+                    .collect::<Vec<_>>();
+                let next = self.rule.step(cell, &neighbours);
+                match next.same(cell) {
+                    true => None,
+                    false => Some((p, cell.alive(), next)),
                 }
+            })
+            .collect::<Vec<_>>();
+        changes
+            .into_iter()
+            .map(|(p, alive, next)| {
+                self.set(&p, next);
+                (p, alive)
+            })
+            .collect()
+    }
+
+    /// Steps the rule until no tile is unstable, returning the changes, the
+    /// number of topplings and of distinct tiles that toppled.
+    fn settle(
+        &mut self,
+        live: &HashSet<Point<Point<u16>, usize>>,
+    ) -> (Changes, u64, usize) {
+        let offsets = self.rule.neighbourhood().offsets();
+        let mut changes = Vec::new();
+        let mut area = HashSet::new();
+        let mut topplings = 0;
+        let mut candidates = live.clone();
+        loop {
+            let unstable = candidates
+                .into_iter()
+                .filter(|p| self.tile(p).map_or(false, |t| self.rule.unstable(t)))
+                .collect::<HashSet<_>>();
+            if unstable.is_empty() {
+                break;
             }
+            topplings += unstable.len() as u64;
+            area.extend(unstable.iter().copied());
+            changes.extend(self.step(&unstable));
+            candidates = unstable
+                .iter()
+                .flat_map(|p| {
+                    let [x, y] = p.coords();
+                    offsets.iter().map(move |(dx, dy)| locate(x + dx, y + dy))
+                })
+                .chain(unstable.iter().copied())
+                .collect();
+        }
+        (changes, topplings, area.len())
+    }
+
+    /// Moves every ant one tile, after it repaints the tile it leaves.
+    fn walk(&mut self) -> Changes {
+        let mut ants = std::mem::take(&mut self.ants);
+        let changes = ants
+            .iter_mut()
+            .map(|ant| {
+                let alive = self.tile(&ant.pos).map_or(false, |t| t.alive());
+                let colour = ant.step(self.colour(&ant.pos));
+                self.paint(&ant.pos, colour);
+                let pos = ant.pos;
+                ant.advance();
+                (pos, alive)
+            })
+            .collect();
+        self.ants = ants;
+        changes
+    }
+
+    /// Births and deaths among `changes`, counting each tile once.
+    fn turnover(
+        &self,
+        changes: Changes,
+    ) -> (usize, usize) {
+        let mut before = HashMap::new();
+        changes.into_iter().for_each(|(p, alive)| {
+            before.entry(p).or_insert(alive);
         });
+        before.into_iter().fold((0, 0), |(births, deaths), (p, was)| {
+            match (was, self.tile(&p).map_or(false, |t| t.alive())) {
+                (false, true) => (births + 1, deaths),
+                (true, false) => (births, deaths + 1),
+                _ => (births, deaths),
+            }
+        })
+    }
 
-        if self.changed {
-            self.hilbert();
-            self.changed = false;
+    /// Places an ant at `pos` with the rule of the last ant placed, or as
+    /// Langton's ant if it's the first.
+    pub fn place_ant(
+        &mut self,
+        pos: &Point<Point<u16>, usize>,
+    ) {
+        let rule = self.ants.last().map_or_else(Turmite::ant, |a| a.rule.clone());
+        self.ants.push(Ant::new(*pos, rule));
+    }
+
+    /// Ant colour of the tile at `pos`: 0 when empty, otherwise one more than
+    /// the palette index of its largest field.
+    pub fn colour(
+        &self,
+        pos: &Point<Point<u16>, usize>,
+    ) -> usize {
+        self.tile(pos)
+            .and_then(|t| t.quantities().into_iter().max_by_key(|f| f.1))
+            .and_then(|f| self.palette.types().iter().position(|t| t.id == f.0))
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Replaces the tile at `pos` with one painted in ant colour `colour`.
+    pub fn paint(
+        &mut self,
+        pos: &Point<Point<u16>, usize>,
+        colour: usize,
+    ) {
+        let mut state = S::default();
+        let field = colour
+            .checked_sub(1)
+            .and_then(|i| self.palette.types().get(i))
+            .map(|t| t.field());
+        if let Some(field) = field {
+            state.put(field);
         }
+        self.set(pos, state);
     }
 
-    pub fn end(&mut self) { self.chunks.clear(); }
+    pub fn set(
+        &mut self,
+        pos: &Point<Point<u16>, usize>,
+        state: S,
+    ) {
+        if !self.chunks.contains_key(&pos.0) {
+            if !state.alive() {
+                return;
+            }
+            let mut chunk = Chunk::default();
+            chunk.pos = pos.0;
+            self.chunks.insert(pos.0, chunk);
+            self.changed = true;
+        }
+        let generation = self.generation;
+        if let Some(chunk) = self.chunks.get_mut(&pos.0) {
+            if !state.alive() {
+                chunk.age[pos.1] = 0;
+            }
+            chunk.tiles[pos.1] = state;
+            chunk.touched[pos.1] = generation;
+            chunk.changed = true;
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot<S> {
+        let tiles = self
+            .chunks
+            .iter()
+            .flat_map(|(&p, chunk)| {
+                chunk
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| t.alive())
+                    .map(move |(i, t)| (Point(p, i), t.clone()))
+            })
+            .collect();
+        Snapshot {
+            generation: self.generation,
+            rule: Some(self.rule.name()),
+            tiles,
+            ants: self.ants.clone(),
+        }
+    }
+
+    /// Returns the name of the rule the snapshot was taken with.
+    pub fn restore(
+        &mut self,
+        snapshot: Snapshot<S>,
+    ) -> Option<String> {
+        self.end();
+        snapshot.tiles.into_iter().for_each(|(pos, state)| {
+            self.set(&pos, state);
+        });
+        self.generation = snapshot.generation;
+        self.ants = snapshot.ants;
+        snapshot.rule
+    }
+
+    pub fn save_world(
+        &self,
+        path: &str,
+    ) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::to_writer(file, &self.snapshot()).map_err(|e| e.to_string())
+    }
+
+    /// Returns the name of the rule the world was saved with.
+    pub fn load_world(
+        &mut self,
+        path: &str,
+    ) -> Result<Option<String>, String> {
+        let json = from_json(path.to_string());
+        let snapshot = serde_json::from_str::<Snapshot<S>>(&json)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(self.restore(snapshot))
+    }
+
+    pub fn end(&mut self) {
+        self.chunks.clear();
+        self.ants.clear();
+        self.history.clear();
+        self.generation = 0;
+    }
 
     pub fn hilbert(&mut self) {
         self.chunks.par_sort_by(|k1, _, k2, _| k1.cmp(&k2));
     }
 }
 
+/// Live tiles and their states, as written by `World::save_world`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "SnapshotDef<S>")]
+pub struct Snapshot<S> {
+    generation: u64,
+    rule:       Option<String>,
+    tiles:      Vec<(Point<Point<u16>, usize>, S)>,
+    ants:       Vec<Ant>,
+}
+/// A snapshot as saved, checked to hold only tiles within their chunks.
+#[derive(Deserialize)]
+struct SnapshotDef<S> {
+    generation: u64,
+    rule:       Option<String>,
+    tiles:      Vec<(Point<Point<u16>, usize>, S)>,
+    #[serde(default)]
+    ants:       Vec<Ant>,
+}
+
+impl<S> TryFrom<SnapshotDef<S>> for Snapshot<S> {
+    type Error = String;
+
+    fn try_from(def: SnapshotDef<S>) -> Result<Self, Self::Error> {
+        match def.tiles.iter().find(|(pos, _)| !pos.in_chunk()) {
+            Some((pos, _)) => Err(format!("no tile {} in a chunk", pos.1)),
+            None => Ok(Self {
+                generation: def.generation,
+                rule:       def.rule,
+                tiles:      def.tiles,
+                ants:       def.ants,
+            }),
+        }
+    }
+}
+
+/// Chunk and tile index at world position `x`, `y`, wrapping around.
+pub fn locate(
+    x: i64,
+    y: i64,
+) -> Point<Point<u16>, usize> {
+    let (x, y) = (x.rem_euclid(SIDE), y.rem_euclid(SIDE));
+    Point(
+        Point((x / 32) as u16, (y / 32) as u16),
+        ((y % 32) * 32 + x % 32) as usize,
+    )
+}
+
+impl Point<Point<u16>, usize> {
+    /// World position, in tiles, of a chunk and tile index.
+    pub fn coords(&self) -> [i64; 2] {
+        let Point(Point(cx, cy), i) = *self;
+        [cx as i64 * 32 + (i % 32) as i64, cy as i64 * 32 + (i / 32) as i64]
+    }
+
+    pub fn in_chunk(&self) -> bool { self.1 < 1024 }
+}
+
 impl Point<u8> {
     pub fn pos(&self) -> usize { (self.0 as u16 * 32 + self.1 as u16) as usize }
 
@@ -350,3 +737,155 @@ impl From<Point<u8>> for Point<f64> {
 impl From<Point<u16>> for Point<f64> {
     fn from(p: Point<u16>) -> Self { Point(p.0 as f64, p.1 as f64) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        logic::{Diffuse, Sandpile, TOPPLE},
+        *,
+    };
+
+    fn fill(
+        world: &mut World,
+        x: i64,
+        y: i64,
+        field: Field,
+    ) {
+        let pos = locate(x, y);
+        let mut tile = world.tile(&pos).cloned().unwrap_or_default();
+        tile += field;
+        world.set(&pos, tile);
+    }
+
+    fn quantity(
+        world: &World,
+        x: i64,
+        y: i64,
+        id: u32,
+    ) -> u32 {
+        world.tile(&locate(x, y)).map_or(0, |t| t.quantity(id))
+    }
+
+    #[test]
+    fn transfer_across_chunks() {
+        let mut world = World::with_rule(Box::new(Collect));
+        fill(&mut world, 31, 5, Field(1, 100));
+        fill(&mut world, 32, 5, Field(1, 7));
+        let moved = world.transfer(&locate(31, 5), &locate(32, 5), Field(1, 40));
+        assert_eq!(moved, 40);
+        assert_eq!(quantity(&world, 31, 5, 1), 60);
+        assert_eq!(quantity(&world, 32, 5, 1), 47);
+        assert_eq!(world.total(1), 107);
+    }
+
+    #[test]
+    fn transfer_across_world_edge() {
+        let mut world = World::with_rule(Box::new(Collect));
+        fill(&mut world, -1, -1, Field(2, 30));
+        let moved = world.transfer(&locate(-1, -1), &locate(0, 0), Field(2, 50));
+        assert_eq!(moved, 30);
+        assert_eq!(quantity(&world, SIDE - 1, SIDE - 1, 2), 0);
+        assert_eq!(quantity(&world, 0, 0, 2), 30);
+        assert_eq!(world.total(2), 30);
+    }
+
+    #[test]
+    fn transfer_into_full_tile() {
+        let mut world = World::with_rule(Box::new(Collect));
+        fill(&mut world, 0, 0, Field(1, 10));
+        fill(&mut world, 1, 0, Field(1, u32::MAX - 4));
+        let total = world.total(1);
+        assert_eq!(world.transfer(&locate(0, 0), &locate(1, 0), Field(1, 10)), 4);
+        assert_eq!(quantity(&world, 0, 0, 1), 6);
+        assert_eq!(world.total(1), total);
+    }
+
+    #[test]
+    fn diffuse_conserves() {
+        let mut world = World::with_rule(Box::new(Diffuse));
+        fill(&mut world, 0, 0, Field(1, 1000));
+        fill(&mut world, 31, 31, Field(2, 500));
+        fill(&mut world, -1, 3, Field(1, 77));
+        let totals = (world.total(1), world.total(2));
+        for _ in 0..10 {
+            world.update();
+            assert_eq!((world.total(1), world.total(2)), totals);
+        }
+    }
+
+    #[test]
+    fn diffuse_conserves_full_tiles() {
+        let mut world = World::with_rule(Box::new(Diffuse));
+        // Without refusing, the centre would keep a remainder of 8 and take
+        // a ninth of `u32::MAX` from each neighbour, overflowing by 4.
+        for (x, y) in (-1..=1).flat_map(|x| (-1..=1).map(move |y| (x, y))) {
+            fill(&mut world, x, y, Field(1, u32::MAX));
+        }
+        world.transfer(&locate(0, 0), &locate(2, 0), Field(1, 4));
+        let total = world.total(1);
+        for _ in 0..3 {
+            world.update();
+            assert_eq!(world.total(1), total);
+        }
+    }
+
+    #[test]
+    fn sandpile_conserves() {
+        let mut world = World::with_rule(Box::new(Sandpile));
+        fill(&mut world, 0, 0, Field(1, 300));
+        fill(&mut world, 32, -1, Field(2, 64));
+        let totals = (world.total(1), world.total(2));
+        world.update();
+        assert_eq!((world.total(1), world.total(2)), totals);
+        let stable = world
+            .chunks
+            .values()
+            .flat_map(|c| c.tiles.iter())
+            .all(|t| t.fields().all(|f| f.1 < TOPPLE));
+        assert!(stable);
+        fill(&mut world, 5, 5, Field(1, 9));
+        world.update();
+        assert_eq!(world.total(1), totals.0 + 9);
+    }
+
+    #[test]
+    fn rejects_tiles_outside_chunks() {
+        let snapshot = |i: usize| {
+            let json = format!(
+                r#"{{"generation": 0, "tiles": [[[[0, 0], {}], 1]]}}"#,
+                i
+            );
+            serde_json::from_str::<Snapshot<multistate::Cell>>(&json)
+        };
+        assert!(snapshot(1023).is_ok());
+        assert!(snapshot(1024).is_err());
+    }
+
+    /// Checks each generation's births and deaths against the live tiles.
+    fn check_turnover<S: CellState>(world: &mut World<S>) {
+        for _ in 0..20 {
+            let before = world.live();
+            world.update();
+            let after = world.live();
+            let stats = world.history().back().unwrap();
+            assert_eq!(stats.births, after.difference(&before).count());
+            assert_eq!(stats.deaths, before.difference(&after).count());
+        }
+    }
+
+    #[test]
+    fn turnover_matches_live_tiles() {
+        use multistate::{BriansBrain, Cell};
+        let mut world = World::<Cell>::with_rule(Box::new(BriansBrain));
+        for &(x, y) in &[(0, 0), (1, 0), (0, 1), (5, 5), (6, 5), (31, 0), (32, 1)] {
+            world.set(&locate(x, y), Cell(1));
+        }
+        world.place_ant(&locate(3, 3));
+        check_turnover(&mut world);
+
+        let mut world = World::with_rule(Box::new(Sandpile));
+        fill(&mut world, 0, 0, Field(1, 300));
+        world.place_ant(&locate(40, 40));
+        check_turnover(&mut world);
+    }
+}
@@ -0,0 +1,151 @@
+use super::{
+    field::Field,
+    logic::CellRule,
+    palette::{FieldType, Palette},
+    state::CellState,
+    table::Table,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Built-in multi-state rules, in the order `NextPreset` cycles through them.
+/// Codd's rules aren't among them until Golly's transition table for them is
+/// bundled; `table:<path>` loads it meanwhile.
+pub const PRESETS: [&str; 3] = ["wireworld", "brians-brain", "langtons-loops"];
+
+/// A cell of a multi-state rule. State 0 is empty; the others are painted
+/// with the palette field of the same id.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Cell(pub u8);
+
+/// Wireworld: electron heads (1) become tails (2), tails become conductor
+/// (3), and conductor next to one or two heads becomes a head.
+#[derive(Copy, Clone, Debug)]
+pub struct Wireworld;
+/// Brian's Brain: off cells (0) with exactly two firing (1) neighbours fire,
+/// firing cells start dying (2), and dying cells switch off.
+#[derive(Copy, Clone, Debug)]
+pub struct BriansBrain;
+
+/// Painting palette of a preset, one field per live state.
+pub fn palette(preset: &str) -> Option<Palette> {
+    let types = match preset {
+        "wireworld" => vec![
+            FieldType::new("conductor", 3, 1, [1., 0.7, 0.1, 1.]),
+            FieldType::new("head", 1, 1, [0.2, 0.5, 1., 1.]),
+            FieldType::new("tail", 2, 1, [1., 0.3, 0.2, 1.]),
+        ],
+        "brians-brain" => vec![
+            FieldType::new("firing", 1, 1, [1., 1., 1., 1.]),
+            FieldType::new("dying", 2, 1, [0.2, 0.4, 1., 1.]),
+        ],
+        "langtons-loops" => vec![
+            FieldType::new("core", 1, 1, [0.2, 0.3, 1., 1.]),
+            FieldType::new("sheath", 2, 1, [1., 0.2, 0.2, 1.]),
+            FieldType::new("state 3", 3, 1, [0.2, 1., 0.2, 1.]),
+            FieldType::new("state 4", 4, 1, [1., 1., 0.2, 1.]),
+            FieldType::new("state 5", 5, 1, [1., 0.2, 1., 1.]),
+            FieldType::new("state 6", 6, 1, [1., 1., 1., 1.]),
+            FieldType::new("state 7", 7, 1, [0.2, 1., 1., 1.]),
+        ],
+        _ => return None,
+    };
+    Some(Palette::new(types))
+}
+
+impl CellState for Cell {
+    /// The presets by name, or `table:<path>` for any other transition table.
+    /// Langton's loops is a table in assets/rules.
+    fn rule(name: &str) -> Result<Box<dyn CellRule<State = Self>>, String> {
+        match name {
+            "default" | "wireworld" => Ok(Box::new(Wireworld)),
+            "brians-brain" => Ok(Box::new(BriansBrain)),
+            "langtons-loops" => Ok(Box::new(Table::load(
+                name,
+                &format!("assets/rules/{}.table", name),
+            )?)),
+            _ if name.starts_with("table:") => {
+                Ok(Box::new(Table::load(name, &name["table:".len()..])?))
+            }
+            _ => Err(format!("unknown multi-state rule: {}", name)),
+        }
+    }
+
+    fn presets() -> &'static [&'static str] { &PRESETS }
+
+    fn palette(rule: &str) -> Option<Palette> { palette(rule) }
+
+    fn alive(&self) -> bool { self.0 != 0 }
+
+    fn same(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self == other
+    }
+
+    fn color(
+        &self,
+        palette: &Palette,
+    ) -> [f32; 4] {
+        palette.color(self.0 as u32)
+    }
+
+    /// Painting sets the state matching the field's id.
+    fn put(
+        &mut self,
+        field: Field,
+    ) {
+        self.0 = field.0 as u8;
+    }
+
+    fn quantities(&self) -> Vec<Field> {
+        match self.alive() {
+            true => vec![Field(self.0 as u32, 1)],
+            false => Vec::new(),
+        }
+    }
+
+    fn inspect(&self) -> Vec<String> { vec![format!("state: {}", self.0)] }
+}
+
+impl CellRule for Wireworld {
+    type State = Cell;
+
+    fn name(&self) -> String { "wireworld".to_string() }
+
+    fn spawns(&self) -> bool { false }
+
+    fn step(
+        &self,
+        cell: &Cell,
+        neighbours: &[&Cell],
+    ) -> Cell {
+        match cell.0 {
+            1 => Cell(2),
+            2 => Cell(3),
+            3 => {
+                let heads = neighbours.iter().filter(|n| n.0 == 1).count();
+                Cell(if heads == 1 || heads == 2 { 1 } else { 3 })
+            }
+            _ => *cell,
+        }
+    }
+}
+impl CellRule for BriansBrain {
+    type State = Cell;
+
+    fn name(&self) -> String { "brians-brain".to_string() }
+
+    fn step(
+        &self,
+        cell: &Cell,
+        neighbours: &[&Cell],
+    ) -> Cell {
+        match cell.0 {
+            0 if neighbours.iter().filter(|n| n.0 == 1).count() == 2 => Cell(1),
+            1 => Cell(2),
+            _ => Cell(0),
+        }
+    }
+}
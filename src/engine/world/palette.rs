@@ -0,0 +1,117 @@
+use super::field::Field;
+use crate::functions::{from_json, write_file};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FieldType {
+    pub name:     String,
+    pub id:       u32,
+    pub quantity: u32,
+    pub color:    [f32; 4],
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Palette {
+    types:  Vec<FieldType>,
+    #[serde(skip)]
+    active: usize,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let types = vec![
+            FieldType::new("red", 1, 10, [1., 0.2, 0.2, 1.]),
+            FieldType::new("green", 2, 10, [0.2, 1., 0.2, 1.]),
+            FieldType::new("blue", 3, 10, [0.2, 0.4, 1., 1.]),
+            FieldType::new("yellow", 4, 10, [1., 1., 0.2, 1.]),
+        ];
+        let active = 0;
+        Self { types, active }
+    }
+}
+impl FieldType {
+    pub fn new(
+        name: &str,
+        id: u32,
+        quantity: u32,
+        color: [f32; 4],
+    ) -> Self {
+        let name = name.to_string();
+        Self {
+            name,
+            id,
+            quantity,
+            color,
+        }
+    }
+
+    pub fn field(&self) -> Field { Field(self.id, self.quantity) }
+}
+impl Palette {
+    pub fn new(types: Vec<FieldType>) -> Self { Self { types, active: 0 } }
+
+    pub fn active(&self) -> &FieldType { &self.types[self.active] }
+
+    pub fn field(&self) -> Field { self.active().field() }
+
+    /// Index of the active field type.
+    pub fn selected(&self) -> usize { self.active }
+
+    pub fn select(
+        &mut self,
+        i: usize,
+    ) {
+        if i < self.types.len() {
+            self.active = i;
+        }
+    }
+
+    pub fn select_id(
+        &mut self,
+        id: u32,
+    ) {
+        if let Some(i) = self.types.iter().position(|t| t.id == id) {
+            self.active = i;
+        }
+    }
+
+    pub fn next(&mut self) { self.active = (self.active + 1) % self.types.len(); }
+
+    pub fn prev(&mut self) {
+        self.active = (self.active + self.types.len() - 1) % self.types.len();
+    }
+
+    pub fn get(
+        &self,
+        id: u32,
+    ) -> Option<&FieldType> {
+        self.types.iter().find(|t| t.id == id)
+    }
+
+    pub fn color(
+        &self,
+        id: u32,
+    ) -> [f32; 4] {
+        self.get(id).map_or([1., 0., 0., 1.], |t| t.color)
+    }
+
+    pub fn types(&self) -> &[FieldType] { &self.types }
+
+    pub fn save_palette(&self) {
+        serde_json::to_writer_pretty(
+            &write_file("assets/config/palette.json".to_string()),
+            &self,
+        )
+        .expect("Couldn't write json to palette.");
+    }
+
+    pub fn load_palette(&mut self) {
+        let path = "assets/config/palette.json".to_string();
+        if let Ok(p) = serde_json::from_str::<Palette>(&from_json(path)) {
+            if !p.types.is_empty() {
+                self.types = p.types;
+                self.active = 0;
+            }
+        };
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fs, path::Path};
+
+/// Live cells of a pattern, relative to its top left corner, with their
+/// states.
+#[derive(Clone, Debug, Default)]
+pub struct Pattern {
+    pub width:      u32,
+    pub height:     u32,
+    pub cells:      Vec<([u32; 2], u8)>,
+    /// Whether cells were written as `A`.. states rather than just live.
+    pub multistate: bool,
+    /// Rule named in the pattern header, if any.
+    pub rule:       Option<String>,
+}
+
+impl Pattern {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let rle = fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_rle(&rle)
+    }
+
+    /// Parses a run length encoded pattern. `b` and `.` are dead cells,
+    /// `A` to `X` are states 1 to 24 of multi-state patterns, any other
+    /// letter is live in state 1, `$` ends a row and `!` ends the pattern.
+    /// Runs may not reach past the size given in the header.
+    pub fn from_rle(rle: &str) -> Result<Self, String> {
+        let mut pattern = Self::default();
+        let mut header = false;
+        let mut size = (u32::MAX, u32::MAX);
+        let (mut x, mut y, mut run) = (0u32, 0u32, 0u32);
+        'lines: for line in rle.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if !header {
+                header = true;
+                if line.starts_with('x') {
+                    for (key, value) in line.split(',').filter_map(|kv| {
+                        let mut kv = kv.splitn(2, '=').map(str::trim);
+                        Some((kv.next()?, kv.next()?))
+                    }) {
+                        match key {
+                            "x" => pattern.width = number(value)?,
+                            "y" => pattern.height = number(value)?,
+                            "rule" => pattern.rule = Some(value.to_string()),
+                            _ => {}
+                        }
+                    }
+                    size = (pattern.width, pattern.height);
+                    continue;
+                }
+            }
+            for c in line.chars() {
+                let n = run.max(1);
+                let end = |from: u32, to: u32| {
+                    from.checked_add(n)
+                        .filter(|&end| end <= to)
+                        .ok_or_else(|| "run too long".to_string())
+                };
+                match c {
+                    '0'..='9' => {
+                        let digit = c.to_digit(10).unwrap();
+                        run = run
+                            .checked_mul(10)
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or_else(|| "run too long".to_string())?;
+                        continue;
+                    }
+                    'b' | '.' => x = end(x, size.0)?,
+                    '$' => {
+                        y = end(y, size.1)?;
+                        x = 0;
+                    }
+                    '!' => break 'lines,
+                    c if c.is_ascii_alphabetic() => {
+                        let state = match c {
+                            'A'..='X' => {
+                                pattern.multistate = true;
+                                c as u8 - b'A' + 1
+                            }
+                            _ => 1,
+                        };
+                        let to = end(x, size.0)?;
+                        if y >= size.1 {
+                            return Err("too many rows".to_string());
+                        }
+                        pattern.cells.extend((x..to).map(|x| ([x, y], state)));
+                        x = to;
+                    }
+                    c if c.is_whitespace() => {}
+                    c => return Err(format!("unexpected {:?} in pattern", c)),
+                }
+                run = 0;
+                pattern.width = pattern.width.max(x);
+                pattern.height = pattern.height.max(y.saturating_add(1));
+            }
+        }
+        Ok(pattern)
+    }
+}
+
+fn number(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("invalid number {} in header", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_runs() {
+        let pattern = Pattern::from_rle("x = 3, y = 2\n3o$b2A!").unwrap();
+        assert_eq!(pattern.cells.len(), 5);
+        assert!(Pattern::from_rle("x = 3, y = 2\n4o!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 2\n2$o!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 2\n99999999999o!").is_err());
+        assert!(Pattern::from_rle("4294967295b2o!").is_err());
+    }
+
+    #[test]
+    fn loads_bundled_patterns() {
+        for entry in fs::read_dir("assets/patterns").unwrap() {
+            let path = entry.unwrap().path();
+            let pattern = Pattern::load(&path).unwrap();
+            assert!(!pattern.cells.is_empty(), "{}", path.display());
+        }
+    }
+}
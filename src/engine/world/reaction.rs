@@ -0,0 +1,295 @@
+use super::{
+    field::Field,
+    logic::{CellRule, Neighbourhood},
+    palette::Palette,
+    state::CellState,
+};
+use crate::functions::{from_json, write_file};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter,
+};
+
+/// Concentrations below this are rounded down to none, so diffusion doesn't
+/// spread a haze of nearly empty tiles across the world.
+const EPSILON: f32 = 1e-3;
+/// Fixed-point scale of the quantities reported as fields.
+pub const SCALE: f32 = 1000.;
+/// Diffusion rate of catalysts painted with a field missing from the config.
+const DIFFUSION: f32 = 0.5;
+
+/// Chemical concentrations in `0..=1`. The substrate is stored as its
+/// depletion, so the default state is the untouched background: full
+/// substrate and no catalysts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Chemicals {
+    /// How far the substrate has been used up.
+    pub depletion: f32,
+    /// Concentration of each catalyst, by field id.
+    pub species:   BTreeMap<u32, f32>,
+}
+/// A catalyst species and how fast it diffuses.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Species {
+    pub field:     u32,
+    pub diffusion: f32,
+}
+/// Diffusion and reaction rates of a Gray-Scott rule. The defaults grow
+/// mitosis-like spots.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReactionConfig {
+    /// Diffusion rate of the substrate.
+    pub substrate: f32,
+    pub species:   Vec<Species>,
+    /// Rate the substrate is replenished.
+    pub feed:      f32,
+    /// Rate the catalysts decay.
+    pub kill:      f32,
+    /// Time step per generation.
+    pub dt:        f32,
+}
+/// Gray-Scott reaction-diffusion: each catalyst turns substrate into more of
+/// itself, `U + 2V -> 3V`, while everything diffuses between neighbours. With
+/// several catalysts they compete for the same substrate.
+#[derive(Clone, Debug)]
+pub struct GrayScott {
+    config: ReactionConfig,
+}
+
+impl Chemicals {
+    pub fn substrate(&self) -> f32 { 1. - self.depletion }
+
+    pub fn get(
+        &self,
+        id: u32,
+    ) -> f32 {
+        self.species.get(&id).copied().unwrap_or(0.)
+    }
+}
+
+impl Default for ReactionConfig {
+    fn default() -> Self {
+        Self {
+            substrate: 1.,
+            species:   vec![Species {
+                field:     1,
+                diffusion: 0.5,
+            }],
+            feed:      0.0367,
+            kill:      0.0649,
+            dt:        1.,
+        }
+    }
+}
+impl ReactionConfig {
+    pub fn save_reaction(&self) {
+        serde_json::to_writer_pretty(
+            &write_file("assets/config/reaction.json".to_string()),
+            &self,
+        )
+        .expect("Couldn't write json to reaction.");
+    }
+
+    pub fn load_reaction(&mut self) {
+        let path = "assets/config/reaction.json".to_string();
+        if let Ok(c) = serde_json::from_str(&from_json(path)) {
+            *self = c;
+        };
+    }
+}
+
+impl GrayScott {
+    pub fn new(config: ReactionConfig) -> Self { Self { config } }
+
+    /// The rule with rates from assets/config/reaction.json.
+    pub fn load() -> Self {
+        let mut config = ReactionConfig::default();
+        config.load_reaction();
+        Self::new(config)
+    }
+
+    fn diffusion(
+        &self,
+        id: u32,
+    ) -> f32 {
+        let species = self.config.species.iter().find(|s| s.field == id);
+        species.map_or(DIFFUSION, |s| s.diffusion)
+    }
+}
+
+/// Discrete laplacian of a quantity over the Moore neighbourhood, in
+/// `Neighbourhood::offsets` order: orthogonal neighbours weigh 0.2 and
+/// diagonal ones 0.05.
+fn laplacian(
+    value: f32,
+    neighbours: impl Iterator<Item = f32>,
+) -> f32 {
+    const WEIGHTS: [f32; 8] = [0.05, 0.2, 0.05, 0.2, 0.2, 0.05, 0.2, 0.05];
+    neighbours.zip(&WEIGHTS).map(|(n, w)| n * w).sum::<f32>() - value
+}
+
+fn settle(value: f32) -> f32 {
+    let value = value.max(0.).min(1.);
+    if value < EPSILON {
+        0.
+    } else {
+        value
+    }
+}
+
+impl CellState for Chemicals {
+    fn rule(name: &str) -> Result<Box<dyn CellRule<State = Self>>, String> {
+        match name {
+            "default" | "gray-scott" => Ok(Box::new(GrayScott::load())),
+            _ => Err(format!("unknown reaction rule: {}", name)),
+        }
+    }
+
+    fn alive(&self) -> bool { self.depletion > 0. || !self.species.is_empty() }
+
+    fn same(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self == other
+    }
+
+    /// Catalyst colours, weighted by concentration.
+    fn color(
+        &self,
+        palette: &Palette,
+    ) -> [f32; 4] {
+        let mut color = [0., 0., 0., 1.];
+        self.species
+            .iter()
+            .filter_map(|(&id, &c)| palette.get(id).map(|t| (t.color, c)))
+            .for_each(|(tc, c)| {
+                color.iter_mut().zip(&tc).take(3).for_each(|(a, b)| *a += b * c)
+            });
+        color.iter_mut().take(3).for_each(|a| *a = a.min(1.));
+        color
+    }
+
+    /// Painting seeds the catalyst into half depleted substrate.
+    fn put(
+        &mut self,
+        field: Field,
+    ) {
+        self.depletion = 0.5;
+        self.species.insert(field.0, 0.25);
+    }
+
+    fn quantities(&self) -> Vec<Field> {
+        self.species
+            .iter()
+            .map(|(&id, &c)| Field(id, (c * SCALE) as u32))
+            .collect()
+    }
+
+    fn inspect(&self) -> Vec<String> {
+        vec![format!("substrate: {:.3}", self.substrate())]
+    }
+}
+
+impl CellRule for GrayScott {
+    type State = Chemicals;
+
+    fn name(&self) -> String { "gray-scott".to_string() }
+
+    fn neighbourhood(&self) -> Neighbourhood { Neighbourhood::Moore }
+
+    fn step(
+        &self,
+        cell: &Chemicals,
+        neighbours: &[&Chemicals],
+    ) -> Chemicals {
+        let ReactionConfig { feed, kill, dt, .. } = self.config;
+        let u = cell.substrate();
+        let ids = iter::once(cell)
+            .chain(neighbours.iter().copied())
+            .flat_map(|c| c.species.keys().copied())
+            .collect::<BTreeSet<_>>();
+        // Substrate used up by each catalyst this step.
+        let reactions = ids
+            .iter()
+            .map(|&id| u * cell.get(id).powi(2))
+            .collect::<Vec<_>>();
+        let d = cell.depletion;
+        let lap = laplacian(d, neighbours.iter().map(|n| n.depletion));
+        let consumed = reactions.iter().sum::<f32>();
+        let depletion =
+            d + dt * (self.config.substrate * lap + consumed - feed * d);
+        let species = ids
+            .into_iter()
+            .zip(reactions)
+            .filter_map(|(id, reaction)| {
+                let v = cell.get(id);
+                let around = neighbours.iter().map(|n| n.get(id));
+                let lap = laplacian(v, around);
+                let growth =
+                    self.diffusion(id) * lap + reaction - (feed + kill) * v;
+                Some(settle(v + dt * growth))
+                    .filter(|&v| v > 0.)
+                    .map(|v| (id, v))
+            })
+            .collect();
+        Chemicals {
+            depletion: settle(depletion),
+            species,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::{locate, World};
+
+    fn unfed(species: Vec<Species>) -> GrayScott {
+        GrayScott::new(ReactionConfig {
+            species,
+            feed: 0.,
+            kill: 0.,
+            ..ReactionConfig::default()
+        })
+    }
+
+    #[test]
+    fn background_stays_fixed() {
+        let background = Chemicals::default();
+        let around = vec![&background; 8];
+        let rule = GrayScott::new(ReactionConfig::default());
+        assert_eq!(rule.step(&background, &around), background);
+    }
+
+    #[test]
+    fn diffusion_conserves_a_spot() {
+        // The reaction turns depletion into catalyst, so without feed and
+        // kill their difference is what diffusion must conserve.
+        let balance = |world: &World<Chemicals>, id: u32| {
+            world
+                .chunks
+                .values()
+                .flat_map(|c| c.tiles.iter())
+                .map(|t| t.get(id) - t.depletion)
+                .sum::<f32>()
+        };
+        let configured = vec![Species {
+            field:     1,
+            diffusion: 0.5,
+        }];
+        for &id in &[1, 2] {
+            let rule = unfed(configured.clone());
+            let mut world = World::<Chemicals>::with_rule(Box::new(rule));
+            let mut spot = Chemicals::default();
+            spot.put(Field(id, 1));
+            world.set(&locate(3, 3), spot);
+            let before = balance(&world, id);
+            world.update();
+            assert!((balance(&world, id) - before).abs() < 1e-5);
+            assert!(world.tile(&locate(4, 3)).unwrap().get(id) > 0.);
+        }
+    }
+}
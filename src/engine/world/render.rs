@@ -0,0 +1,88 @@
+use super::{chunk::Chunk, palette::Palette, state::CellState};
+
+/// Generations over which the activity view fades a changed tile out.
+pub const ACTIVITY: u64 = 16;
+/// Quantity at which the heatmap reaches the middle of its colour ramp.
+pub const HEAT_SCALE: f32 = 100.;
+/// Age, in generations, at which the age view reaches the middle of its ramp.
+pub const AGE_SCALE: f32 = 100.;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderMode {
+    Fields,
+    Heatmap,
+    Age,
+    Activity,
+}
+
+pub struct Painter<'a> {
+    pub mode:       RenderMode,
+    pub palette:    &'a Palette,
+    pub generation: u64,
+}
+
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Fields => RenderMode::Heatmap,
+            RenderMode::Heatmap => RenderMode::Age,
+            RenderMode::Age => RenderMode::Activity,
+            RenderMode::Activity => RenderMode::Fields,
+        }
+    }
+
+    /// Whether tile colours change every generation even if no field does.
+    pub fn animated(self) -> bool {
+        self == RenderMode::Age || self == RenderMode::Activity
+    }
+}
+impl<'a> Painter<'a> {
+    pub fn color<S: CellState>(
+        &self,
+        chunk: &Chunk<S>,
+        i: usize,
+    ) -> [f32; 4] {
+        let tile = &chunk.tiles[i];
+        match self.mode {
+            RenderMode::Fields => tile.color(self.palette),
+            RenderMode::Heatmap => {
+                let id = self.palette.active().id;
+                let quantities = tile.quantities();
+                let q = quantities.iter().find(|f| f.0 == id).map_or(0, |f| f.1);
+                ramp(q as f32 / (q as f32 + HEAT_SCALE))
+            }
+            RenderMode::Age => {
+                let age = chunk.age[i] as f32;
+                ramp(age / (age + AGE_SCALE))
+            }
+            RenderMode::Activity => {
+                let since = self.generation.saturating_sub(chunk.touched[i]);
+                if since < ACTIVITY {
+                    ramp(1. - since as f32 / ACTIVITY as f32)
+                } else {
+                    [0.2, 0.2, 0.2, 1.]
+                }
+            }
+        }
+    }
+}
+
+/// Maps `v` in `0..=1` from dark blue through green and yellow to red.
+pub fn ramp(v: f32) -> [f32; 4] {
+    const STOPS: [[f32; 3]; 4] = [
+        [0.1, 0.1, 0.5],
+        [0.1, 0.8, 0.3],
+        [1.0, 0.9, 0.1],
+        [1.0, 0.1, 0.1],
+    ];
+    let v = v.max(0.).min(1.) * (STOPS.len() - 1) as f32;
+    let i = (v.floor() as usize).min(STOPS.len() - 2);
+    let t = v - i as f32;
+    let (a, b) = (STOPS[i], STOPS[i + 1]);
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        1.,
+    ]
+}
@@ -0,0 +1,140 @@
+use super::{field::Field, logic::CellRule, tile::Tile};
+
+use rhai::{Array, Dynamic, Engine, Scope, AST, INT};
+use std::{cell::RefCell, fs, time::SystemTime};
+
+/// Script used as the world's rule at startup, if it exists.
+pub const PATH: &str = "assets/config/rule.rhai";
+
+/// A rule written in Rhai, recompiled whenever its file changes.
+///
+/// The script defines `step(fields, neighbours)`. `fields` holds the tile's
+/// fields as `[id, quantity]` pairs, `neighbours` the fields of the eight
+/// surrounding tiles, row by row from the top left. It returns the new
+/// fields; an empty array empties the tile.
+pub struct ScriptRule {
+    engine:    Engine,
+    ast:       Option<AST>,
+    path:      String,
+    modified:  Option<SystemTime>,
+    /// First compile or runtime error since the last `CellRule::error`.
+    error:     RefCell<Option<String>>,
+}
+
+impl ScriptRule {
+    pub fn new(path: &str) -> Self {
+        let mut rule = Self {
+            engine:   Engine::new(),
+            ast:      None,
+            path:     path.to_string(),
+            modified: None,
+            error:    RefCell::new(None),
+        };
+        rule.reload();
+        rule
+    }
+
+    /// Whether a script compiled, so `step` runs it.
+    pub fn active(&self) -> bool { self.ast.is_some() }
+
+    /// Recompiles the script if its file changed since it was last read. A
+    /// script that doesn't compile leaves the previous one running.
+    pub fn reload(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let engine = &self.engine;
+        let compiled = fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|src| engine.compile(&src).map_err(|e| e.to_string()));
+        match compiled {
+            Ok(ast) => self.ast = Some(ast),
+            Err(e) => {
+                *self.error.get_mut() = Some(format!("{}: {}", self.path, e))
+            }
+        }
+    }
+
+    /// Runs the script's `step` on plain field lists.
+    pub fn run(
+        &self,
+        fields: &[Field],
+        neighbours: &[Vec<Field>],
+    ) -> Result<Vec<Field>, String> {
+        let ast = match &self.ast {
+            Some(ast) => ast,
+            None => return Ok(fields.to_vec()),
+        };
+        let neighbours = neighbours
+            .iter()
+            .map(|n| Dynamic::from(to_array(n)))
+            .collect::<Array>();
+        let args = (to_array(fields), neighbours);
+        let out: Array = self
+            .engine
+            .call_fn(&mut Scope::new(), ast, "step", args)
+            .map_err(|e| format!("{}: {}", self.path, e))?;
+        let pair = |f: Dynamic| match f.try_cast::<Array>().as_deref() {
+            Some([id, q]) => Ok(Field(int(id)? as u32, int(q)?.max(0) as u32)),
+            _ => Err(format!("{}: expected [id, quantity]", self.path)),
+        };
+        out.into_iter()
+            .map(pair)
+            .filter(|f| f.as_ref().map_or(true, |f| f.1 > 0))
+            .collect()
+    }
+}
+
+fn to_array(fields: &[Field]) -> Array {
+    fields
+        .iter()
+        .map(|f| {
+            let pair = vec![Dynamic::from(f.0 as INT), Dynamic::from(f.1 as INT)];
+            Dynamic::from(pair)
+        })
+        .collect()
+}
+
+fn int(value: &Dynamic) -> Result<INT, String> {
+    value.as_int().map_err(|t| format!("expected an integer, got {}", t))
+}
+
+impl CellRule for ScriptRule {
+    type State = Tile;
+
+    fn name(&self) -> String { format!("script:{}", self.path) }
+
+    /// Leaves the tile as it is if the script fails.
+    fn step(
+        &self,
+        cell: &Tile,
+        neighbours: &[&Tile],
+    ) -> Tile {
+        if self.error.borrow().is_some() {
+            return cell.clone();
+        }
+        let fields = |t: &Tile| {
+            let mut fields = t.fields().copied().collect::<Vec<_>>();
+            fields.sort();
+            fields
+        };
+        let neighbours = neighbours.iter().map(|n| fields(n)).collect::<Vec<_>>();
+        match self.run(&fields(cell), &neighbours) {
+            Ok(new) => {
+                let mut next = Tile::default();
+                new.into_iter().for_each(|f| next += f);
+                next
+            }
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                cell.clone()
+            }
+        }
+    }
+
+    fn prepare(&mut self) { self.reload(); }
+
+    fn error(&mut self) -> Option<String> { self.error.get_mut().take() }
+}
@@ -0,0 +1,49 @@
+use super::{field::Field, logic::CellRule, palette::Palette};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// State of a single cell. `Chunk` and `World` are generic over it, with the
+/// field-based `Tile` as the default.
+pub trait CellState:
+    Clone + Default + Send + Serialize + DeserializeOwned + 'static
+{
+    /// Rule for this state by name, as typed in the console or saved with a
+    /// world. `default` names the rule a new world starts with.
+    fn rule(name: &str) -> Result<Box<dyn CellRule<State = Self>>, String>;
+
+    /// Names of the built-in rules `NextPreset` cycles through.
+    fn presets() -> &'static [&'static str] { &[] }
+
+    /// Painting palette that comes with a rule, replacing the world's when
+    /// the rule is chosen.
+    fn palette(_rule: &str) -> Option<Palette> { None }
+
+    /// Whether the cell is alive: stepped, counted and drawn. The default
+    /// state never is.
+    fn alive(&self) -> bool;
+
+    /// Whether two states are equal, so unchanged cells aren't rewritten.
+    fn same(
+        &self,
+        other: &Self,
+    ) -> bool;
+
+    /// Colour of a live cell in the fields render mode.
+    fn color(
+        &self,
+        palette: &Palette,
+    ) -> [f32; 4];
+
+    /// Adds a palette field to the cell, as when painting with the mouse.
+    fn put(
+        &mut self,
+        field: Field,
+    );
+
+    /// Quantities of the fields the cell holds, for stats, picking and the
+    /// heatmap. States without fields hold none.
+    fn quantities(&self) -> Vec<Field> { Vec::new() }
+
+    /// Lines describing the cell in the inspector, besides its fields.
+    fn inspect(&self) -> Vec<String> { Vec::new() }
+}
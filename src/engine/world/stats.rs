@@ -0,0 +1,62 @@
+use super::palette::Palette;
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub generation: u64,
+    pub tiles:      usize,
+    pub chunks:     usize,
+    pub births:     usize,
+    pub deaths:     usize,
+    /// Topplings in the generation's avalanche, for rules that settle.
+    pub avalanche:  u64,
+    /// Distinct tiles that toppled in the avalanche.
+    pub area:       usize,
+    pub fields:     BTreeMap<u32, u64>,
+}
+
+impl Stats {
+    pub fn field(
+        &self,
+        id: u32,
+    ) -> u64 {
+        self.fields.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn csv_header(
+        out: &mut impl Write,
+        palette: &Palette,
+    ) -> io::Result<()> {
+        write!(out, "generation,tiles,chunks,births,deaths,avalanche,area")?;
+        for t in palette.types() {
+            write!(out, ",{}", t.name)?;
+        }
+        writeln!(out)
+    }
+
+    pub fn csv_row(
+        &self,
+        out: &mut impl Write,
+        palette: &Palette,
+    ) -> io::Result<()> {
+        write!(
+            out,
+            "{},{},{},{},{},{},{}",
+            self.generation,
+            self.tiles,
+            self.chunks,
+            self.births,
+            self.deaths,
+            self.avalanche,
+            self.area
+        )?;
+        for t in palette.types() {
+            write!(out, ",{}", self.field(t.id))?;
+        }
+        writeln!(out)
+    }
+}
@@ -0,0 +1,390 @@
+use super::{
+    logic::{CellRule, Neighbourhood},
+    multistate::Cell,
+};
+
+use std::{collections::HashMap, fs};
+
+/// How a transition matches rearranged neighbourhoods.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Symmetry {
+    None,
+    /// Rotations by a quarter turn.
+    Rotate4,
+    /// Rotations by an eighth of a turn, Moore only.
+    Rotate8,
+    /// Quarter turn rotations and their mirror images.
+    Rotate4Reflect,
+    /// Any order of the neighbours.
+    Permute,
+}
+/// A multi-state rule read from a transition table in Golly's `@TABLE`
+/// format. Cells without a matching transition keep their state.
+#[derive(Clone, Debug)]
+pub struct Table {
+    name:          String,
+    neighbourhood: Neighbourhood,
+    symmetry:      Symmetry,
+    /// Next state keyed by the cell's state followed by its neighbours',
+    /// clockwise from the north.
+    transitions:   HashMap<Vec<u8>, u8>,
+}
+
+impl Table {
+    /// Reads the table at `path`, naming the rule `name` so that it can be
+    /// found again by `Cell::rule`.
+    pub fn load(
+        name: &str,
+        path: &str,
+    ) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(name, &text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Parses `n_states`, `neighborhood`, `symmetries`, `var` and transition
+    /// lines, from the whole text or from its `@TABLE` section. Transitions
+    /// are written either as one digit per state or as comma separated states
+    /// and variables, cell first and next state last. A variable used twice
+    /// in a transition takes the same state both times. Earlier transitions
+    /// take precedence.
+    pub fn parse(
+        name: &str,
+        text: &str,
+    ) -> Result<Self, String> {
+        let mut table = Self {
+            name:          name.to_string(),
+            neighbourhood: Neighbourhood::VonNeumann,
+            symmetry:      Symmetry::None,
+            transitions:   HashMap::new(),
+        };
+        let mut states = 256;
+        let mut vars = HashMap::new();
+        // Golly's .rule files hold the table in their @TABLE section.
+        let mut skip = false;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let error = |e: String| format!("line {}: {}", n + 1, e);
+            if line.starts_with('@') {
+                skip = !line.starts_with("@TABLE");
+            }
+            if line.is_empty() || line.starts_with('@') || skip {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("n_states:") {
+                states = value.trim().parse().map_err(|_| {
+                    error(format!("invalid state count {}", value))
+                })?;
+            } else if let Some(value) = line.strip_prefix("neighborhood:") {
+                table.neighbourhood = match value.trim() {
+                    "vonNeumann" => Neighbourhood::VonNeumann,
+                    "Moore" => Neighbourhood::Moore,
+                    n => return Err(error(format!("unknown neighborhood {}", n))),
+                };
+            } else if let Some(value) = line.strip_prefix("symmetries:") {
+                table.symmetry = match value.trim() {
+                    "none" => Symmetry::None,
+                    "rotate4" => Symmetry::Rotate4,
+                    "rotate8" => Symmetry::Rotate8,
+                    "rotate4reflect" => Symmetry::Rotate4Reflect,
+                    "permute" => Symmetry::Permute,
+                    s => return Err(error(format!("unknown symmetry {}", s))),
+                };
+            } else if let Some(var) = line.strip_prefix("var ") {
+                let mut parts = var.splitn(2, '=').map(str::trim);
+                let var = parts.next().unwrap_or("").to_string();
+                let values = parts
+                    .next()
+                    .and_then(|v| v.strip_prefix('{')?.strip_suffix('}'))
+                    .ok_or_else(|| error(format!("invalid var {}", var)))?;
+                let values = values
+                    .split(',')
+                    .map(|v| state(v.trim(), states, &vars))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?
+                    .concat();
+                vars.insert(var, values);
+            } else {
+                table.transition(line, states, &vars).map_err(error)?;
+            }
+        }
+        Ok(table)
+    }
+
+    fn transition(
+        &mut self,
+        line: &str,
+        states: usize,
+        vars: &HashMap<String, Vec<u8>>,
+    ) -> Result<(), String> {
+        let tokens: Vec<String> = match line.contains(',') {
+            true => line.split(',').map(|t| t.trim().to_string()).collect(),
+            false => line.chars().map(String::from).collect(),
+        };
+        let ring = self.ring();
+        if tokens.len() != ring + 2 {
+            return Err(format!("expected {} states in {}", ring + 2, line));
+        }
+        // Each distinct variable is bound to every one of its states in turn.
+        let mut bound = tokens
+            .iter()
+            .filter(|t| vars.contains_key(*t))
+            .collect::<Vec<_>>();
+        bound.sort();
+        bound.dedup();
+        let mut choice = vec![0; bound.len()];
+        loop {
+            let cells = tokens
+                .iter()
+                .map(|t| match bound.iter().position(|b| *b == t) {
+                    Some(i) => Ok(vars[t][choice[i]]),
+                    None => Ok(state(t, states, vars)?[0]),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let (key, next) = cells.split_at(ring + 1);
+            self.symmetric(key).into_iter().for_each(|key| {
+                self.transitions.entry(key).or_insert(next[0]);
+            });
+            // Advances the bindings like an odometer.
+            let mut i = 0;
+            while i < bound.len() {
+                choice[i] += 1;
+                if choice[i] < vars[bound[i]].len() {
+                    break;
+                }
+                choice[i] = 0;
+                i += 1;
+            }
+            if i == bound.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Number of neighbours.
+    fn ring(&self) -> usize {
+        match self.neighbourhood {
+            Neighbourhood::Moore => 8,
+            _ => 4,
+        }
+    }
+
+    /// Keys matching `key` under the table's symmetry. Permuted tables sort
+    /// the neighbours instead, as `step` does when looking them up.
+    fn symmetric(
+        &self,
+        key: &[u8],
+    ) -> Vec<Vec<u8>> {
+        let (cell, ring) = (key[0], &key[1..]);
+        let n = ring.len();
+        let rotate = |by: usize| {
+            let mut k = vec![cell];
+            k.extend((0..n).map(|i| ring[(i + by) % n]));
+            k
+        };
+        let reflect = |k: &[u8]| {
+            let mut r = vec![k[0]];
+            r.extend((0..n).map(|i| k[1 + (n - i) % n]));
+            r
+        };
+        match self.symmetry {
+            Symmetry::None => vec![key.to_vec()],
+            Symmetry::Rotate4 => (0..4).map(|i| rotate(i * n / 4)).collect(),
+            Symmetry::Rotate8 => (0..n).map(rotate).collect(),
+            Symmetry::Rotate4Reflect => (0..4)
+                .map(|i| rotate(i * n / 4))
+                .flat_map(|k| vec![reflect(&k), k])
+                .collect(),
+            Symmetry::Permute => {
+                let mut k = key.to_vec();
+                k[1..].sort_unstable();
+                vec![k]
+            }
+        }
+    }
+}
+
+/// States a table token stands for: a state number or a variable.
+fn state(
+    token: &str,
+    states: usize,
+    vars: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    if let Some(values) = vars.get(token) {
+        return Ok(values.clone());
+    }
+    match token.parse::<usize>() {
+        Ok(s) if s < states && s <= u8::MAX as usize => Ok(vec![s as u8]),
+        _ => Err(format!("invalid state {}", token)),
+    }
+}
+
+impl CellRule for Table {
+    type State = Cell;
+
+    fn name(&self) -> String { self.name.clone() }
+
+    fn neighbourhood(&self) -> Neighbourhood { self.neighbourhood }
+
+    fn step(
+        &self,
+        cell: &Cell,
+        neighbours: &[&Cell],
+    ) -> Cell {
+        // Indices into `neighbours`, which come row by row from the top left,
+        // clockwise from the north.
+        const VON_NEUMANN: [usize; 4] = [0, 2, 3, 1];
+        const MOORE: [usize; 8] = [1, 2, 4, 7, 6, 5, 3, 0];
+        let order: &[usize] = match self.neighbourhood {
+            Neighbourhood::Moore => &MOORE,
+            _ => &VON_NEUMANN,
+        };
+        let mut key = vec![cell.0];
+        key.extend(order.iter().map(|&i| neighbours[i].0));
+        if self.symmetry == Symmetry::Permute {
+            key[1..].sort_unstable();
+        }
+        Cell(self.transitions.get(&key).copied().unwrap_or(cell.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::{locate, pattern::Pattern, World};
+
+    /// Next state of `cell` with neighbours `ring`, listed clockwise from the
+    /// north, passed to `step` in row order as the world does.
+    fn next(
+        table: &Table,
+        cell: u8,
+        ring: &[u8],
+    ) -> u8 {
+        let rows = match *ring {
+            [n, e, s, w] => vec![n, w, e, s],
+            [n, ne, e, se, s, sw, w, nw] => vec![nw, n, ne, w, e, sw, s, se],
+            _ => panic!("bad ring {:?}", ring),
+        };
+        let cells: Vec<Cell> = rows.into_iter().map(Cell).collect();
+        table.step(&Cell(cell), &cells.iter().collect::<Vec<_>>()).0
+    }
+
+    fn von_neumann(
+        symmetries: &str,
+        lines: &str,
+    ) -> Table {
+        let text = format!(
+            "n_states:8\nneighborhood:vonNeumann\nsymmetries:{}\n{}",
+            symmetries, lines
+        );
+        Table::parse("test", &text).unwrap()
+    }
+
+    fn moore(symmetries: &str) -> Table {
+        let text = format!(
+            "n_states:8\nneighborhood:Moore\nsymmetries:{}\n{}",
+            symmetries, "0,1,2,3,4,5,6,7,0,7"
+        );
+        Table::parse("test", &text).unwrap()
+    }
+
+    /// The Moore ring of `moore` turned clockwise by `by` eighths.
+    fn turned(by: usize) -> Vec<u8> {
+        (0..8).map(|i| ((i + 8 - by) % 8 + 1) as u8 % 8).collect()
+    }
+
+    #[test]
+    fn loads_langtons_loops() {
+        let path = "assets/rules/langtons-loops.table";
+        let table = Table::load("langtons-loops", path).unwrap();
+        assert_eq!(table.neighbourhood, Neighbourhood::VonNeumann);
+        assert_eq!(table.symmetry, Symmetry::Rotate4);
+        // 000012: an empty cell with a core cell to its west becomes sheath.
+        for ring in &[[0, 0, 0, 1], [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0]] {
+            assert_eq!(next(&table, 0, ring), 2);
+        }
+    }
+
+    #[test]
+    fn neighbours_run_clockwise_from_north() {
+        let table = von_neumann("none", "012345");
+        assert_eq!(next(&table, 0, &[1, 2, 3, 4]), 5);
+        assert_eq!(next(&table, 0, &[2, 3, 4, 1]), 0);
+        let table = moore("none");
+        assert_eq!(next(&table, 0, &turned(0)), 7);
+        assert_eq!(next(&table, 0, &turned(1)), 0);
+    }
+
+    #[test]
+    fn rotations_and_reflections() {
+        let turns = [[4, 1, 2, 3], [3, 4, 1, 2], [2, 3, 4, 1]];
+        let mirrored = [1, 4, 3, 2];
+        let table = von_neumann("rotate4", "012345");
+        for ring in &turns {
+            assert_eq!(next(&table, 0, ring), 5);
+        }
+        assert_eq!(next(&table, 0, &mirrored), 0);
+        let table = von_neumann("rotate4reflect", "012345");
+        for ring in &turns {
+            assert_eq!(next(&table, 0, ring), 5);
+        }
+        assert_eq!(next(&table, 0, &mirrored), 5);
+        assert_eq!(next(&table, 0, &[1, 3, 2, 4]), 0);
+        let table = von_neumann("permute", "012345");
+        assert_eq!(next(&table, 0, &[1, 3, 2, 4]), 5);
+        assert_eq!(next(&table, 0, &[1, 3, 3, 4]), 0);
+        let table = moore("rotate4");
+        assert_eq!(next(&table, 0, &turned(2)), 7);
+        assert_eq!(next(&table, 0, &turned(6)), 7);
+        assert_eq!(next(&table, 0, &turned(1)), 0);
+        let table = moore("rotate8");
+        for by in 0..8 {
+            assert_eq!(next(&table, 0, &turned(by)), 7);
+        }
+        assert_eq!(next(&table, 0, &[1, 0, 7, 6, 5, 4, 3, 2]), 0);
+    }
+
+    #[test]
+    fn variables_bind_once_per_transition() {
+        let lines = "var a={1,2}\n0,a,a,0,0,3\n0,1,2,0,0,4\n0,1,1,0,0,5";
+        let table = von_neumann("none", lines);
+        assert_eq!(next(&table, 0, &[1, 1, 0, 0]), 3);
+        assert_eq!(next(&table, 0, &[2, 2, 0, 0]), 3);
+        assert_eq!(next(&table, 0, &[1, 2, 0, 0]), 4);
+        assert_eq!(next(&table, 0, &[2, 1, 0, 0]), 0);
+    }
+
+    #[test]
+    fn reads_golly_rule_files() {
+        let text = "@RULE test\nA test rule.\n@TABLE\nn_states:8\n\
+                    neighborhood:vonNeumann\nsymmetries:none\n012345\n\
+                    @COLORS\n1 255 0 0\n";
+        let table = Table::parse("test", text).unwrap();
+        assert_eq!(next(&table, 0, &[1, 2, 3, 4]), 5);
+    }
+
+    #[test]
+    fn langtons_loop_reproduces() {
+        let path = "assets/rules/langtons-loops.table";
+        let table = Table::load("langtons-loops", path).unwrap();
+        let mut world = World::<Cell>::with_rule(Box::new(table));
+        let loop_ = Pattern::load("assets/patterns/langtons-loop.rle").unwrap();
+        let (width, height) = (loop_.width as i64, loop_.height as i64);
+        for &([x, y], state) in &loop_.cells {
+            world.set(&locate(x as i64, y as i64), Cell(state));
+        }
+        let states = |world: &World<Cell>, dx: i64| {
+            (0..width * height)
+                .map(|i| locate(dx + i % width, i / width))
+                .map(|pos| world.tile(&pos).map_or(0, |c| c.0))
+                .collect::<Vec<_>>()
+        };
+        let start = states(&world, 0);
+        for _ in 0..151 {
+            world.update();
+        }
+        // The daughter loop has the starting shape, 11 tiles to the east.
+        assert_eq!(states(&world, 11), start);
+        assert_ne!(states(&world, 0), start);
+    }
+}
@@ -1,37 +1,25 @@
-use super::{field::Field, logic::Rule, Point};
+use super::{
+    field::Field,
+    logic::{self, CellRule, Rule},
+    palette::Palette,
+    state::CellState,
+};
 
-use hilbert::Point as HPoint;
-use indexmap::Equivalent;
-use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use std::{
-    cmp::{min, Ordering},
     collections::HashSet,
-    convert::TryFrom,
-    hash::{Hash, Hasher},
     ops::{Add, AddAssign},
 };
 
-#[derive(Eq, Clone, Debug, Default)]
+/// The field-based cell state: quantities of any number of palette fields.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Tile {
-    pub pos:     Point<u8>,
     pub members: u16,
     pub rule:    Option<Rule>,
     fields:      HashSet<Field>,
 }
 
 impl Tile {
-    pub fn new(&pos: &Point<u8>) -> Self {
-        let members = 0;
-        let fields = HashSet::new();
-        let rule = None;
-        Self {
-            pos,
-            members,
-            rule,
-            fields,
-        }
-    }
-
     pub fn add_field(
         &mut self,
         field: Field,
@@ -56,96 +44,109 @@ impl Tile {
         }
     }
 
-    pub fn hilbert_index(
+    pub fn color(
         &self,
-        &i: &usize,
-    ) -> u64 {
-        let a = HPoint::new(i, &[self.pos.0 as u32, self.pos.1 as u32])
-            .hilbert_transform(8)
-            .to_radix_be(8);
-
-        TryFrom::try_from(BigUint::from_radix_be(&a, 8).unwrap())
-            .expect("Tile hilbert index overflow?")
-    }
-
-    pub fn test(&mut self) -> Self {
-        use rand::Rng;
-        for i in 1..4 {
-            self.add_field(Field(i + 1, rand::thread_rng().gen_range(1..8)));
-        }
-        self.clone()
-    }
-
-    pub fn color(&self) -> [f32; 4] {
+        palette: &Palette,
+    ) -> [f32; 4] {
         let mut c: [f32; 4] = [1., 0., 0., 1.];
-        if self.members != 0 {
-            let v = self
-                .fields
-                .iter()
-                .take(self.fields.len())
-                .map(|&a| a.1 as f32)
-                .collect::<Vec<f32>>();
-            let s = min(v.len(), 3);
-            let m = v[0..s].iter().fold(0f32, |mut acc, &a| {
-                acc += a;
-                acc
+        let total = self.fields.iter().map(|f| f.1 as f32).sum::<f32>();
+        if self.members != 0 && total > 0. {
+            c = [0.; 4];
+            self.fields.iter().for_each(|f| {
+                let w = f.1 as f32 / total;
+                c.iter_mut()
+                    .zip(palette.color(f.0).iter())
+                    .for_each(|(a, &b)| *a += b * w);
             });
-            // dbg!(&m);
-            c[1..=s].copy_from_slice(&v[0..s]);
-            c[1..=s].iter_mut().for_each(|a| *a = (*a % 255.) / 255.);
         }
         c
     }
 
-    pub fn pos(&self) -> usize { Point::<u8>::pos(&self.pos) }
-}
+    pub fn fields(&self) -> impl Iterator<Item = &Field> { self.fields.iter() }
 
-impl Ord for Tile {
-    fn cmp(
+    /// Quantity held of field `id`, zero if the tile doesn't hold it.
+    pub fn quantity(
         &self,
-        other: &Self,
-    ) -> Ordering {
-        self.hilbert_index(&0).cmp(&&other.hilbert_index(&1))
+        id: u32,
+    ) -> u32 {
+        self.fields.get(&Field(id, 0)).map_or(0, |f| f.1)
     }
-}
-impl PartialOrd for Tile {
-    fn partial_cmp(
-        &self,
-        other: &Self,
-    ) -> Option<Ordering> {
-        Some(self.cmp(other))
+
+    /// Removes up to `field.1` of a field, dropping the field once it runs
+    /// out, and returns the quantity removed.
+    pub fn take(
+        &mut self,
+        field: Field,
+    ) -> u32 {
+        let have = self.quantity(field.0);
+        let taken = have.min(field.1);
+        match taken == have {
+            true => self.remove_field(field),
+            false => {
+                self.fields.replace(Field(field.0, have - taken));
+            }
+        }
+        taken
     }
-}
-impl PartialEq for Tile {
-    fn eq(
+
+    pub fn has(
         &self,
-        other: &Self,
+        id: u32,
     ) -> bool {
-        self.pos == other.pos
+        self.fields.contains(&Field(id, 0))
     }
-}
-impl Hash for Tile {
-    fn hash<H: Hasher>(
+
+    /// Whether both tiles hold the same quantities of the same fields.
+    pub fn same_fields(
         &self,
-        state: &mut H,
-    ) {
-        self.pos.hash(state);
+        other: &Tile,
+    ) -> bool {
+        self.members == other.members &&
+            self.fields
+                .iter()
+                .all(|f| other.fields.get(f).map_or(false, |o| o.1 == f.1))
     }
 }
-impl Equivalent<Point<u8>> for Tile {
-    fn equivalent(
+
+impl CellState for Tile {
+    fn rule(name: &str) -> Result<Box<dyn CellRule<State = Self>>, String> {
+        logic::parse(name)
+    }
+
+    fn presets() -> &'static [&'static str] {
+        &["Collect", "Spread", "Multiply", "Diffuse", "Sandpile", "B3/S23"]
+    }
+
+    fn alive(&self) -> bool { self.members > 0 }
+
+    fn same(
         &self,
-        key: &Point<u8>,
+        other: &Self,
     ) -> bool {
-        &self.pos == key
+        self.same_fields(other)
     }
-}
-impl Equivalent<Tile> for Point<u8> {
-    fn equivalent(
+
+    fn color(
         &self,
-        key: &Tile,
-    ) -> bool {
-        self == &key.pos
+        palette: &Palette,
+    ) -> [f32; 4] {
+        Tile::color(self, palette)
+    }
+
+    fn put(
+        &mut self,
+        field: Field,
+    ) {
+        *self += field;
+    }
+
+    fn quantities(&self) -> Vec<Field> { self.fields.iter().copied().collect() }
+
+    fn inspect(&self) -> Vec<String> {
+        vec![
+            format!("members: {}", self.members),
+            format!("rule: {:?}", self.rule),
+        ]
     }
 }
 impl Add<Field> for Tile {
@@ -1,20 +1,19 @@
-use std::fs::File;
+use std::{fs::File, io};
 
-pub fn read_file(name: String) -> File {
-    if let Ok(file) = File::open(&name) {
-        file
-    } else {
-        match File::create(&name) {
-            Ok(file2) => file2,
-            Err(e) => panic!("{}", e),
-        }
+pub fn read_file(name: String) -> io::Result<File> { File::open(&name) }
+pub fn write_file(name: String) -> File {
+    match File::create(&name) {
+        Ok(file) => file,
+        Err(e) => panic!("{}", e),
     }
 }
+/// Contents of a json file, or an empty string if it doesn't exist.
 pub fn from_json(name: String) -> String {
     use std::io::Read;
     let mut contents = String::new();
-    let mut file = read_file(name);
-    file.read_to_string(&mut contents)
-        .expect("Couldn't read json file.");
+    if let Ok(mut file) = read_file(name) {
+        file.read_to_string(&mut contents)
+            .expect("Couldn't read json file.");
+    }
     contents
 }